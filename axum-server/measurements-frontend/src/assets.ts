@@ -1,11 +1,17 @@
 import * as t from 'io-ts'
 import { DateFromISOString } from 'io-ts-types'
 
-export const MeasurementCodec = t.type({
-  temperature: t.number,
-  humidity: t.number,
-  date: DateFromISOString,
-})
+export const MeasurementCodec = t.intersection([
+  t.type({
+    date: DateFromISOString,
+  }),
+  // temperature only probes don't measure humidity,
+  // particulate matter sensors don't measure either
+  t.partial({
+    temperature: t.number,
+    humidity: t.number,
+  }),
+])
 
 export type Measurement = t.TypeOf<typeof MeasurementCodec>
 
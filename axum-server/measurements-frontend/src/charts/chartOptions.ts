@@ -62,12 +62,14 @@ function timeAxis(
 export function calculateMeasurementAxisMinMax(
   measurements: Measurement[],
   defaultMinMax: MeasurementAxisMinMax,
-  callback: (measurement: Measurement) => number,
+  callback: (measurement: Measurement) => number | undefined,
 ): MeasurementAxisMinMax {
-  if (measurements.length === 0) {
+  const measurementsForType = measurements
+    .map(callback)
+    .filter((value): value is number => value !== undefined)
+  if (measurementsForType.length === 0) {
     return defaultMinMax
   }
-  const measurementsForType = measurements.map(callback)
   const minMeasured = Math.floor(Math.min(...measurementsForType))
   const maxMeasured = Math.ceil(Math.max(...measurementsForType))
 
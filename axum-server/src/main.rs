@@ -3,17 +3,19 @@ use axum::{
     extract::{Path, State},
     http::{Method, StatusCode, Uri, header},
     response::{Html, IntoResponse, Response, Result},
-    routing::{get, post},
+    routing::{get, post, put},
 };
 use axum_extra::{
     TypedHeader,
     extract::OptionalQuery,
     headers::{Authorization, authorization::Basic},
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use include_dir::{Dir, include_dir};
+use psychrometrics::Psychrometrics;
 use ringbuffer::{AllocRingBuffer, RingBuffer};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tokio::signal::unix::{SignalKind, signal};
 use tower_http::cors::{Any, CorsLayer};
@@ -25,22 +27,282 @@ static STATIC_CONTENT_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/static-co
 const USER: &str = env!("REST_USER");
 const PASSWORD: &str = env!("REST_USER_PASSWORD");
 
+// how far a device clock may run ahead of the server clock
+const MAX_CLOCK_SKEW: TimeDelta = TimeDelta::minutes(5);
+// device timestamps from before 2025 come from a clock that was never set
+const MIN_DEVICE_TIMESTAMP_MILLIS: i64 = 1_735_689_600_000;
+const MAX_BATCH_SIZE: usize = 100;
+const MEASUREMENTS_PER_DEVICE: usize = 5000;
+// firmware from before device ids is stored under this id
+const DEFAULT_DEVICE: &str = "default";
+// bounds the memory held by measurements from unknown devices
+const MAX_DEVICES: usize = 32;
+// sampling intervals the firmware accepts, in seconds
+const SAMPLING_INTERVAL_SECS: std::ops::RangeInclusive<u64> = 1..=3600;
+// sensor indices and calibrated DS18B20 probes the firmware has room for
+const SENSOR_COUNT: u8 = 9;
+const MAX_PROBE_CALIBRATIONS: usize = 16;
+
 #[derive(Deserialize)]
 struct CreateMeasurement {
-    temperature: f64,
-    humidity: f64,
+    #[serde(default = "default_device")]
+    device: String,
+    // firmware posting from a single sensor doesn't send an index
+    #[serde(default)]
+    sensor: u8,
+    // only sent for DS18B20 probes
+    probe: Option<ProbeId>,
+    // not sent for particulate matter sensors
+    temperature: Option<f64>,
+    // not sent for temperature only sensors
+    humidity: Option<f64>,
+    // only sent by firmware with a barometric sensor
+    pressure: Option<f64>,
+    // only sent by firmware with a CO2 sensor
+    co2_ppm: Option<u16>,
+    // only sent by firmware with a particulate matter sensor
+    pm1_0: Option<u16>,
+    pm2_5: Option<u16>,
+    pm10: Option<u16>,
+    // outliers the firmware rejected since its boot, only sent for filtered sensors
+    rejected: Option<u32>,
+    // version of the calibration applied by the firmware, not sent for raw values
+    calibration: Option<u16>,
+    // unix time in milliseconds, only sent by firmware with a synchronised clock
+    timestamp: Option<i64>,
+}
+
+// ROM of a DS18B20 probe, sent as 16 lowercase hex digits like the device id
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ProbeId(u64);
+
+impl Serialize for ProbeId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:016x}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for ProbeId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != 16 || !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return Err(serde::de::Error::custom("probe must be 16 hex digits"));
+        }
+        u64::from_str_radix(&hex, 16)
+            .map(ProbeId)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl CreateMeasurement {
+    fn has_values(&self) -> bool {
+        self.temperature.is_some()
+            || self.humidity.is_some()
+            || self.pressure.is_some()
+            || self.co2_ppm.is_some()
+            || self.pm1_0.is_some()
+            || self.pm2_5.is_some()
+            || self.pm10.is_some()
+    }
+
+    fn to_measurement(&self, date: DateTime<Utc>) -> Measurement {
+        Measurement {
+            date,
+            sensor: self.sensor,
+            probe: self.probe,
+            temperature: self.temperature,
+            humidity: self.humidity,
+            pressure: self.pressure,
+            co2_ppm: self.co2_ppm,
+            pm1_0: self.pm1_0,
+            pm2_5: self.pm2_5,
+            pm10: self.pm10,
+            rejected: self.rejected,
+            calibration: self.calibration,
+        }
+    }
+}
+
+// outcome for each item of a batch, in the order they were sent
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum BatchItemResult {
+    Created { date: DateTime<Utc> },
+    Rejected { reason: &'static str },
 }
 
 #[derive(Clone, Copy, Debug, Serialize)]
 struct Measurement {
     date: DateTime<Utc>,
-    temperature: f64,
-    humidity: f64,
+    sensor: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    probe: Option<ProbeId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    humidity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pressure: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    co2_ppm: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pm1_0: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pm2_5: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pm10: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rejected: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    calibration: Option<u16>,
+}
+
+// a stored measurement, optionally extended by metrics derived from temperature and humidity
+#[derive(Serialize)]
+struct MeasurementResponse {
+    device: String,
+    #[serde(flatten)]
+    measurement: Measurement,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    derived: Option<Psychrometrics>,
+}
+
+impl MeasurementResponse {
+    fn new(device: &str, measurement: Measurement, derived: bool) -> Self {
+        let derived = match (derived, measurement.temperature, measurement.humidity) {
+            (true, Some(temperature), Some(humidity)) => {
+                Some(Psychrometrics::new(temperature as f32, humidity as f32))
+            }
+            _ => None,
+        };
+        MeasurementResponse {
+            device: device.to_string(),
+            measurement,
+            derived,
+        }
+    }
+}
+
+// a linear correction `raw * gain + offset`, left out values keep the identity
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct Calibration {
+    temperature_offset: f32,
+    temperature_gain: f32,
+    humidity_offset: f32,
+    humidity_gain: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            temperature_offset: 0.0,
+            temperature_gain: 1.0,
+            humidity_offset: 0.0,
+            humidity_gain: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    fn is_valid(&self) -> bool {
+        [
+            self.temperature_offset,
+            self.temperature_gain,
+            self.humidity_offset,
+            self.humidity_gain,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+    }
+}
+
+// the calibration of a sensor or of a single DS18B20 probe
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct CalibrationEntry {
+    sensor: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    probe: Option<ProbeId>,
+    #[serde(default)]
+    calibration: Calibration,
+}
+
+// stored by the firmware whenever the version changes
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CalibrationConfig {
+    version: u16,
+    sensors: Vec<CalibrationEntry>,
+}
+
+impl CalibrationConfig {
+    fn is_valid(&self) -> bool {
+        let probes = self
+            .sensors
+            .iter()
+            .filter(|entry| entry.probe.is_some())
+            .count();
+        probes <= MAX_PROBE_CALIBRATIONS
+            && self.sensors.len() - probes <= SENSOR_COUNT as usize
+            && self
+                .sensors
+                .iter()
+                .all(|entry| entry.sensor < SENSOR_COUNT && entry.calibration.is_valid())
+    }
+}
+
+// runtime config fetched by the firmware, unset values keep the firmware defaults
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct DeviceConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling_interval_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    calibration: Option<CalibrationConfig>,
+}
+
+impl DeviceConfig {
+    fn is_valid(&self) -> bool {
+        self.sampling_interval_secs
+            .is_none_or(|secs| SAMPLING_INTERVAL_SECS.contains(&secs))
+            && self
+                .calibration
+                .as_ref()
+                .is_none_or(CalibrationConfig::is_valid)
+    }
+}
+
+// wifi link counters the firmware keeps since its boot
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct LinkDiagnostics {
+    reconnects: u32,
+    link_losses: u32,
+    lease_losses: u32,
+    failed_joins: u32,
+}
+
+#[derive(Deserialize)]
+struct CreateDiagnostics {
+    #[serde(default = "default_device")]
+    device: String,
+    link: LinkDiagnostics,
+}
+
+// the latest diagnostics a device reported
+#[derive(Clone, Serialize)]
+struct DiagnosticsResponse {
+    device: String,
+    date: DateTime<Utc>,
+    link: LinkDiagnostics,
 }
 
+// the measurements of each device, keyed by its id
+type DeviceMeasurements = HashMap<String, AllocRingBuffer<Measurement>>;
+
 #[derive(Clone)]
 struct AppState {
-    measurements: Arc<Mutex<AllocRingBuffer<Measurement>>>,
+    measurements: Arc<Mutex<DeviceMeasurements>>,
+    // the config of each device, the default device's config applies to devices without one
+    configs: Arc<Mutex<HashMap<String, DeviceConfig>>>,
+    diagnostics: Arc<Mutex<HashMap<String, DiagnosticsResponse>>>,
 }
 
 #[derive(Debug)]
@@ -48,11 +310,33 @@ enum MeasurementError {
     NotFound,
     Unreadable,
     Unauthorized,
+    BatchTooLarge,
+    InvalidConfig,
+    InvalidDevice,
+    TooManyDevices,
 }
 
 #[derive(Deserialize)]
 struct Params {
     downsample: Option<usize>,
+    // measurements of all devices if unset
+    device: Option<String>,
+    // include dew point, heat index, absolute humidity and comfort
+    #[serde(default)]
+    derived: bool,
+}
+
+#[derive(Deserialize)]
+struct ConfigParams {
+    #[serde(default = "default_device")]
+    device: String,
+}
+
+#[derive(Deserialize)]
+struct LatestParams {
+    device: Option<String>,
+    #[serde(default)]
+    derived: bool,
 }
 
 #[derive(Serialize)]
@@ -78,6 +362,26 @@ impl IntoResponse for MeasurementError {
                 warn!("{}", message);
                 (StatusCode::UNAUTHORIZED, message)
             }
+            Self::BatchTooLarge => {
+                let message = "Too many measurements in the batch.";
+                warn!("{}", message);
+                (StatusCode::PAYLOAD_TOO_LARGE, message)
+            }
+            Self::InvalidConfig => {
+                let message = "Sampling interval outside 1-3600 seconds or invalid calibration.";
+                warn!("{}", message);
+                (StatusCode::UNPROCESSABLE_ENTITY, message)
+            }
+            Self::InvalidDevice => {
+                let message = "Device must be 16 lowercase hex digits or default.";
+                warn!("{}", message);
+                (StatusCode::UNPROCESSABLE_ENTITY, message)
+            }
+            Self::TooManyDevices => {
+                let message = "No room for another device.";
+                warn!("{}", message);
+                (StatusCode::FORBIDDEN, message)
+            }
         };
         (
             status,
@@ -118,7 +422,9 @@ async fn main() {
         .init();
 
     let state = AppState {
-        measurements: Arc::new(Mutex::new(AllocRingBuffer::new(5000))),
+        measurements: Arc::new(Mutex::new(HashMap::new())),
+        configs: Arc::new(Mutex::new(HashMap::new())),
+        diagnostics: Arc::new(Mutex::new(HashMap::new())),
     };
 
     let cors = CorsLayer::new()
@@ -132,6 +438,11 @@ async fn main() {
         .route("/api/measurements/latest", get(latest_measurement))
         .route("/api/measurements", get(query_measurements))
         .route("/api/measurements", post(create_measurement))
+        .route("/api/measurements/batch", post(create_measurements))
+        .route("/api/config", get(device_config))
+        .route("/api/config", put(update_device_config))
+        .route("/api/diagnostics", get(query_diagnostics))
+        .route("/api/diagnostics", post(create_diagnostics))
         .with_state(state)
         .fallback(fallback)
         .layer(cors);
@@ -161,23 +472,64 @@ async fn fallback(uri: Uri) -> impl IntoResponse {
 
 async fn latest_measurement(
     State(state): State<AppState>,
-) -> Result<Json<Measurement>, MeasurementError> {
-    let measurements = state
+    OptionalQuery(params): OptionalQuery<LatestParams>,
+) -> Result<Json<MeasurementResponse>, MeasurementError> {
+    let devices = state
         .measurements
         .lock()
         .map_err(|_| MeasurementError::Unreadable)?;
-
-    match measurements.back() {
-        Some(measurements) => Ok(Json(*measurements)),
+    let device = params.as_ref().and_then(|params| params.device.as_deref());
+    let derived = params.as_ref().is_some_and(|params| params.derived);
+
+    let latest = match device {
+        Some(device) => devices
+            .get_key_value(device)
+            .and_then(|(device, measurements)| Some((device, *measurements.back()?))),
+        None => devices
+            .iter()
+            .filter_map(|(device, measurements)| Some((device, *measurements.back()?)))
+            .max_by_key(|(_, measurement)| measurement.date),
+    };
+    match latest {
+        Some((device, measurement)) => {
+            Ok(Json(MeasurementResponse::new(device, measurement, derived)))
+        }
         None => Err(MeasurementError::NotFound),
     }
 }
 
+// the measurements of one device, or of all devices ordered by date
+fn device_measurements<'a>(
+    devices: &'a DeviceMeasurements,
+    device: Option<&str>,
+) -> Vec<(&'a str, Measurement)> {
+    match device {
+        Some(device) => devices
+            .get_key_value(device)
+            .map(|(device, measurements)| {
+                measurements
+                    .iter()
+                    .map(|measurement| (device.as_str(), *measurement))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => {
+            let mut measurements: Vec<_> = devices
+                .iter()
+                .flat_map(|(device, measurements)| {
+                    measurements
+                        .iter()
+                        .map(move |measurement| (device.as_str(), *measurement))
+                })
+                .collect();
+            measurements.sort_by_key(|(_, measurement)| measurement.date);
+            measurements
+        }
+    }
+}
+
 // decimation with interval offset
-fn downsample_measurements(
-    measurements: Vec<Measurement>,
-    wanted_count: usize,
-) -> Vec<Measurement> {
+fn downsample_measurements<T: Copy>(measurements: Vec<T>, wanted_count: usize) -> Vec<T> {
     if measurements.is_empty() || wanted_count == 0 || wanted_count >= measurements.len() {
         return measurements;
     }
@@ -196,20 +548,28 @@ fn downsample_measurements(
 async fn query_measurements(
     State(state): State<AppState>,
     OptionalQuery(params): OptionalQuery<Params>,
-) -> Result<Json<Vec<Measurement>>, MeasurementError> {
-    let measurements_guard = state
+) -> Result<Json<Vec<MeasurementResponse>>, MeasurementError> {
+    let devices = state
         .measurements
         .lock()
         .map_err(|_| MeasurementError::Unreadable)?;
-    let mut measurements = measurements_guard.iter().copied().collect();
+    let device = params.as_ref().and_then(|params| params.device.as_deref());
+    let mut measurements = device_measurements(&devices, device);
     if let Some(Params {
         downsample: Some(wanted_count),
+        ..
     }) = params
     {
         measurements = downsample_measurements(measurements, wanted_count);
     }
+    let derived = params.as_ref().is_some_and(|params| params.derived);
 
-    Ok(Json(measurements))
+    Ok(Json(
+        measurements
+            .into_iter()
+            .map(|(device, measurement)| MeasurementResponse::new(device, measurement, derived))
+            .collect(),
+    ))
 }
 
 async fn version() -> Json<Version> {
@@ -240,22 +600,232 @@ async fn create_measurement(
     auth: Option<TypedHeader<Authorization<Basic>>>,
     State(state): State<AppState>,
     Json(payload): Json<CreateMeasurement>,
-) -> Result<(StatusCode, Json<Measurement>), MeasurementError> {
+) -> Result<(StatusCode, Json<MeasurementResponse>), MeasurementError> {
     validate_authorization(auth)?;
+    if !is_valid_device(&payload.device) {
+        return Err(MeasurementError::InvalidDevice);
+    }
 
-    let measurement = Measurement {
-        date: Utc::now(),
-        temperature: payload.temperature,
-        humidity: payload.humidity,
-    };
-    let mut measurements = state
+    let date = measurement_date(payload.timestamp, Utc::now());
+    let measurement = payload.to_measurement(date);
+    let mut devices = state
         .measurements
         .lock()
         .map_err(|_| MeasurementError::Unreadable)?;
-    measurements.enqueue(measurement);
-    debug!("new measurement: {:?}", measurement);
+    store_measurement(&mut devices, &payload.device, measurement)
+        .map_err(|_| MeasurementError::TooManyDevices)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MeasurementResponse::new(
+            &payload.device,
+            measurement,
+            false,
+        )),
+    ))
+}
+
+// a new device is only added while there is room for it
+fn store_measurement(
+    devices: &mut DeviceMeasurements,
+    device: &str,
+    measurement: Measurement,
+) -> Result<(), &'static str> {
+    if !devices.contains_key(device) && devices.len() >= MAX_DEVICES {
+        return Err("too many devices");
+    }
+    devices
+        .entry(device.to_string())
+        .or_insert_with(|| AllocRingBuffer::new(MEASUREMENTS_PER_DEVICE))
+        .enqueue(measurement);
+    debug!("new measurement from {}: {:?}", device, measurement);
+    Ok(())
+}
+
+// the firmware sends its chip id as 16 lowercase hex digits, older firmware sends none
+fn is_valid_device(device: &str) -> bool {
+    device == DEFAULT_DEVICE
+        || (device.len() == 16
+            && device
+                .bytes()
+                .all(|digit| matches!(digit, b'0'..=b'9' | b'a'..=b'f')))
+}
+
+fn default_device() -> String {
+    DEFAULT_DEVICE.to_string()
+}
+
+// firmware sends batches from its buffer, the items are validated one by one
+async fn create_measurements(
+    auth: Option<TypedHeader<Authorization<Basic>>>,
+    State(state): State<AppState>,
+    Json(payload): Json<Vec<CreateMeasurement>>,
+) -> Result<Json<Vec<BatchItemResult>>, MeasurementError> {
+    validate_authorization(auth)?;
+    if payload.len() > MAX_BATCH_SIZE {
+        return Err(MeasurementError::BatchTooLarge);
+    }
+
+    let received = Utc::now();
+    let mut devices = state
+        .measurements
+        .lock()
+        .map_err(|_| MeasurementError::Unreadable)?;
+    let results = payload
+        .into_iter()
+        .map(|item| {
+            let stored = validate_batch_item(&item, received).and_then(|date| {
+                store_measurement(&mut devices, &item.device, item.to_measurement(date))?;
+                Ok(date)
+            });
+            match stored {
+                Ok(date) => BatchItemResult::Created { date },
+                Err(reason) => {
+                    warn!(
+                        "rejected measurement from sensor {} of {}: {}",
+                        item.sensor, item.device, reason
+                    );
+                    BatchItemResult::Rejected { reason }
+                }
+            }
+        })
+        .collect();
+
+    Ok(Json(results))
+}
+
+// batched measurements are late by design, so the time of receipt can't stand in for a bad timestamp
+fn validate_batch_item(
+    item: &CreateMeasurement,
+    received: DateTime<Utc>,
+) -> Result<DateTime<Utc>, &'static str> {
+    if !is_valid_device(&item.device) {
+        return Err("invalid device");
+    }
+    if !item.has_values() {
+        return Err("no values");
+    }
+    if item
+        .humidity
+        .is_some_and(|humidity| !(0.0..=100.0).contains(&humidity))
+    {
+        return Err("humidity out of range");
+    }
+    match item.timestamp {
+        Some(timestamp) => device_date(timestamp, received).ok_or("implausible timestamp"),
+        None => Ok(received),
+    }
+}
+
+// the device timestamp if it is plausible, otherwise the time of receipt
+fn measurement_date(timestamp: Option<i64>, received: DateTime<Utc>) -> DateTime<Utc> {
+    let Some(timestamp) = timestamp else {
+        return received;
+    };
+    device_date(timestamp, received).unwrap_or_else(|| {
+        warn!(
+            "implausible device timestamp {}, using the server time",
+            timestamp
+        );
+        received
+    })
+}
+
+fn device_date(timestamp: i64, received: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(timestamp).filter(|date| {
+        timestamp >= MIN_DEVICE_TIMESTAMP_MILLIS && *date <= received + MAX_CLOCK_SKEW
+    })
+}
+
+// a device without a config of its own gets the default config
+async fn device_config(
+    State(state): State<AppState>,
+    OptionalQuery(params): OptionalQuery<ConfigParams>,
+) -> Result<Json<DeviceConfig>, MeasurementError> {
+    let device = params.map_or_else(default_device, |params| params.device);
+    if !is_valid_device(&device) {
+        return Err(MeasurementError::InvalidDevice);
+    }
+
+    let configs = state
+        .configs
+        .lock()
+        .map_err(|_| MeasurementError::Unreadable)?;
+    let config = configs
+        .get(&device)
+        .or_else(|| configs.get(DEFAULT_DEVICE))
+        .cloned()
+        .unwrap_or_default();
+    Ok(Json(config))
+}
+
+async fn update_device_config(
+    auth: Option<TypedHeader<Authorization<Basic>>>,
+    State(state): State<AppState>,
+    OptionalQuery(params): OptionalQuery<ConfigParams>,
+    Json(payload): Json<DeviceConfig>,
+) -> Result<Json<DeviceConfig>, MeasurementError> {
+    validate_authorization(auth)?;
+    let device = params.map_or_else(default_device, |params| params.device);
+    if !is_valid_device(&device) {
+        return Err(MeasurementError::InvalidDevice);
+    }
+    if !payload.is_valid() {
+        return Err(MeasurementError::InvalidConfig);
+    }
+
+    let mut configs = state
+        .configs
+        .lock()
+        .map_err(|_| MeasurementError::Unreadable)?;
+    if !configs.contains_key(&device) && configs.len() >= MAX_DEVICES {
+        return Err(MeasurementError::TooManyDevices);
+    }
+    info!("new config of {}: {:?}", device, payload);
+    configs.insert(device, payload.clone());
+
+    Ok(Json(payload))
+}
+
+// the latest diagnostics of all devices, ordered by device
+async fn query_diagnostics(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DiagnosticsResponse>>, MeasurementError> {
+    let diagnostics = state
+        .diagnostics
+        .lock()
+        .map_err(|_| MeasurementError::Unreadable)?;
+    let mut diagnostics: Vec<_> = diagnostics.values().cloned().collect();
+    diagnostics.sort_by(|a, b| a.device.cmp(&b.device));
+    Ok(Json(diagnostics))
+}
+
+async fn create_diagnostics(
+    auth: Option<TypedHeader<Authorization<Basic>>>,
+    State(state): State<AppState>,
+    Json(payload): Json<CreateDiagnostics>,
+) -> Result<(StatusCode, Json<DiagnosticsResponse>), MeasurementError> {
+    validate_authorization(auth)?;
+    if !is_valid_device(&payload.device) {
+        return Err(MeasurementError::InvalidDevice);
+    }
+
+    let mut diagnostics = state
+        .diagnostics
+        .lock()
+        .map_err(|_| MeasurementError::Unreadable)?;
+    if !diagnostics.contains_key(&payload.device) && diagnostics.len() >= MAX_DEVICES {
+        return Err(MeasurementError::TooManyDevices);
+    }
+    let response = DiagnosticsResponse {
+        device: payload.device,
+        date: Utc::now(),
+        link: payload.link,
+    };
+    info!("diagnostics of {}: {:?}", response.device, response.link);
+    diagnostics.insert(response.device.clone(), response.clone());
 
-    Ok((StatusCode::CREATED, Json(measurement)))
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 async fn static_content(Path(path): Path<String>) -> Result<impl IntoResponse, StaticContentError> {
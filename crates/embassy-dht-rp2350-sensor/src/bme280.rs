@@ -0,0 +1,245 @@
+//! Async driver for the Bosch BME280 I2C temperature, humidity and pressure sensor.
+//!
+//! The sensor is used in forced mode with 1x oversampling: every read triggers a single
+//! measurement, after which the sensor goes back to sleep. Raw values are compensated with the
+//! integer formulas from the datasheet and the factory calibration of the sensor.
+
+use crate::sensor::{EnvironmentalSensor, SensorError, SensorReading, SensorValues};
+use crate::DHTSensorError;
+use embassy_time::Duration;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+// SDO connected to GND. The address is 0x77 with SDO connected to VDDIO.
+pub const DEFAULT_ADDRESS: u8 = 0x76;
+
+const CHIP_ID: u8 = 0x60;
+
+const REGISTER_CALIBRATION_TP: u8 = 0x88;
+const REGISTER_CHIP_ID: u8 = 0xD0;
+const REGISTER_RESET: u8 = 0xE0;
+const REGISTER_CALIBRATION_H: u8 = 0xE1;
+const REGISTER_CTRL_HUM: u8 = 0xF2;
+const REGISTER_CTRL_MEAS: u8 = 0xF4;
+const REGISTER_DATA: u8 = 0xF7;
+
+const RESET: u8 = 0xB6;
+const RESET_US: u32 = 2_000;
+
+const OVERSAMPLING_X1: u8 = 0b001;
+const FORCED_MODE: u8 = 0b01;
+// Maximum measurement time with 1x oversampling of all three values.
+const MEASUREMENT_US: u32 = 9_300;
+
+/// Factory calibration, read once from the non-volatile memory of the sensor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+    h1: u8,
+    h2: i16,
+    h3: u8,
+    h4: i16,
+    h5: i16,
+    h6: i8,
+}
+
+impl Calibration {
+    /// Parses the registers 0x88-0xA1 and 0xE1-0xE7.
+    pub fn from_registers(tp: &[u8; 26], h: &[u8; 7]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([tp[i], tp[i + 1]]);
+        let i16_at = |i: usize| i16::from_le_bytes([tp[i], tp[i + 1]]);
+        Calibration {
+            t1: u16_at(0),
+            t2: i16_at(2),
+            t3: i16_at(4),
+            p1: u16_at(6),
+            p2: i16_at(8),
+            p3: i16_at(10),
+            p4: i16_at(12),
+            p5: i16_at(14),
+            p6: i16_at(16),
+            p7: i16_at(18),
+            p8: i16_at(20),
+            p9: i16_at(22),
+            h1: tp[25],
+            h2: i16::from_le_bytes([h[0], h[1]]),
+            h3: h[2],
+            // 12 bit values sharing the nibbles of 0xE5
+            h4: ((h[3] as i8 as i16) << 4) | (h[4] & 0x0F) as i16,
+            h5: ((h[5] as i8 as i16) << 4) | (h[4] >> 4) as i16,
+            h6: h[6] as i8,
+        }
+    }
+
+    /// Returns the temperature in 0.01 degrees and the fine resolution temperature that the
+    /// pressure and humidity compensation depend on. Computed in 64 bits, the 32 bit formula of
+    /// the datasheet overflows for raw values and coefficients at the ends of their ranges.
+    fn temperature(&self, adc: i32) -> (i32, i32) {
+        let adc = adc as i64;
+        let t1 = self.t1 as i64;
+        let var1 = (((adc >> 3) - (t1 << 1)) * self.t2 as i64) >> 11;
+        let var2 = (((((adc >> 4) - t1) * ((adc >> 4) - t1)) >> 12) * self.t3 as i64) >> 14;
+        let t_fine = (var1 + var2).clamp(i32::MIN as i64 / 5, i32::MAX as i64 / 5);
+        (((t_fine * 5 + 128) >> 8) as i32, t_fine as i32)
+    }
+
+    /// Pressure in Pa as an unsigned 24.8 fixed point number. Even 64 bits overflow with
+    /// coefficients at the ends of their ranges, the arithmetic wraps like the C reference does.
+    fn pressure(&self, adc: i32, t_fine: i32) -> u32 {
+        let mut var1 = t_fine as i64 - 128_000;
+        let mut var2 = var1.wrapping_mul(var1).wrapping_mul(self.p6 as i64);
+        var2 = var2.wrapping_add(var1.wrapping_mul(self.p5 as i64) << 17);
+        var2 = var2.wrapping_add((self.p4 as i64) << 35);
+        var1 = (var1.wrapping_mul(var1).wrapping_mul(self.p3 as i64) >> 8)
+            .wrapping_add(var1.wrapping_mul(self.p2 as i64) << 12);
+        var1 = (1i64 << 47).wrapping_add(var1).wrapping_mul(self.p1 as i64) >> 33;
+        if var1 == 0 {
+            // Avoid a division by zero with an unprogrammed calibration.
+            return 0;
+        }
+        let mut p = 1_048_576 - adc as i64;
+        p = ((p << 31).wrapping_sub(var2).wrapping_mul(3125)).wrapping_div(var1);
+        var1 = (self.p9 as i64).wrapping_mul(p >> 13).wrapping_mul(p >> 13) >> 25;
+        var2 = (self.p8 as i64).wrapping_mul(p) >> 19;
+        ((p.wrapping_add(var1).wrapping_add(var2) >> 8).wrapping_add((self.p7 as i64) << 4)) as u32
+    }
+
+    /// Relative humidity in % as an unsigned 22.10 fixed point number, in 64 bits like the
+    /// temperature.
+    fn humidity(&self, adc: i32, t_fine: i32) -> u32 {
+        let adc = adc as i64;
+        let mut v = t_fine as i64 - 76_800;
+        v = ((((adc << 14) - ((self.h4 as i64) << 20) - (self.h5 as i64 * v)) + 16_384) >> 15)
+            * (((((((v * self.h6 as i64) >> 10) * (((v * self.h3 as i64) >> 11) + 32_768))
+                >> 10)
+                + 2_097_152)
+                * self.h2 as i64
+                + 8_192)
+                >> 14);
+        v -= ((((v >> 15) * (v >> 15)) >> 7) * self.h1 as i64) >> 4;
+        (v.clamp(0, 419_430_400) >> 12) as u32
+    }
+
+    /// Compensates the burst read of the data registers 0xF7-0xFE.
+    pub fn compensate(&self, data: &[u8; 8]) -> SensorValues {
+        let adc_p = ((data[0] as i32) << 12) | ((data[1] as i32) << 4) | (data[2] as i32 >> 4);
+        let adc_t = ((data[3] as i32) << 12) | ((data[4] as i32) << 4) | (data[5] as i32 >> 4);
+        let adc_h = ((data[6] as i32) << 8) | data[7] as i32;
+
+        let (temperature, t_fine) = self.temperature(adc_t);
+        SensorValues {
+            temperature: Some(temperature as f32 / 100.0),
+            humidity: Some(self.humidity(adc_h, t_fine) as f32 / 1024.0),
+            // Pa to hPa
+            pressure: Some(self.pressure(adc_p, t_fine) as f32 / 25_600.0),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Bme280<I, D> {
+    i2c: I,
+    delay: D,
+    address: u8,
+    calibration: Option<Calibration>,
+}
+
+impl<I: I2c, D: DelayNs> Bme280<I, D> {
+    pub fn new(i2c: I, delay: D) -> Self {
+        Self::new_with_address(i2c, delay, DEFAULT_ADDRESS)
+    }
+
+    pub fn new_with_address(i2c: I, delay: D, address: u8) -> Self {
+        Bme280 {
+            i2c,
+            delay,
+            address,
+            calibration: None,
+        }
+    }
+
+    pub fn release(self) -> (I, D) {
+        (self.i2c, self.delay)
+    }
+
+    // Resets the sensor and reads its calibration. Called by the first measurement if it
+    // hasn't been called before.
+    pub async fn init(&mut self) -> Result<(), DHTSensorError> {
+        let mut chip_id = [0u8];
+        self.read_registers(REGISTER_CHIP_ID, &mut chip_id).await?;
+        if chip_id[0] != CHIP_ID {
+            return Err(DHTSensorError::InvalidData);
+        }
+        self.write_register(REGISTER_RESET, RESET).await?;
+        self.delay.delay_us(RESET_US).await;
+
+        let mut tp = [0u8; 26];
+        self.read_registers(REGISTER_CALIBRATION_TP, &mut tp)
+            .await?;
+        let mut h = [0u8; 7];
+        self.read_registers(REGISTER_CALIBRATION_H, &mut h).await?;
+        self.calibration = Some(Calibration::from_registers(&tp, &h));
+        Ok(())
+    }
+
+    pub async fn measure(&mut self) -> Result<SensorValues, DHTSensorError> {
+        if self.calibration.is_none() {
+            self.init().await?;
+        }
+        // ctrl_hum only takes effect after a write to ctrl_meas.
+        self.write_register(REGISTER_CTRL_HUM, OVERSAMPLING_X1)
+            .await?;
+        self.write_register(
+            REGISTER_CTRL_MEAS,
+            (OVERSAMPLING_X1 << 5) | (OVERSAMPLING_X1 << 2) | FORCED_MODE,
+        )
+        .await?;
+        self.delay.delay_us(MEASUREMENT_US).await;
+
+        let mut data = [0u8; 8];
+        self.read_registers(REGISTER_DATA, &mut data).await?;
+        match &self.calibration {
+            Some(calibration) => Ok(calibration.compensate(&data)),
+            None => Err(DHTSensorError::NoData),
+        }
+    }
+
+    async fn read_registers(
+        &mut self,
+        register: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), DHTSensorError> {
+        self.i2c
+            .write_read(self.address, &[register], buffer)
+            .await
+            .map_err(|_| DHTSensorError::BusError)
+    }
+
+    async fn write_register(&mut self, register: u8, value: u8) -> Result<(), DHTSensorError> {
+        self.i2c
+            .write(self.address, &[register, value])
+            .await
+            .map_err(|_| DHTSensorError::BusError)
+    }
+}
+
+impl<I: I2c, D: DelayNs> EnvironmentalSensor for Bme280<I, D> {
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_micros(MEASUREMENT_US as u64)
+    }
+
+    async fn read(&mut self) -> Result<SensorReading, SensorError> {
+        Ok(SensorReading::fresh(self.measure().await?))
+    }
+}
@@ -0,0 +1,83 @@
+//! Hardware independent decoding of the 40-bit DHT frame.
+//!
+//! A frame is laid out as 16 bits of humidity, 16 bits of temperature and an 8 bit checksum,
+//! most significant bit first. The checksum is the low byte of the sum of the four data bytes.
+
+use crate::{DHTSensorError, DTHResponse, SensorModel};
+
+pub const FRAME_BITS: usize = 40;
+
+const MAX_HUMIDITY: f32 = 100.0;
+
+/// Builds a frame from the 32 data bits and the checksum byte, as they are delivered by the PIO.
+pub fn frame(data: u32, checksum: u8) -> u64 {
+    ((data as u64) << 8) | checksum as u64
+}
+
+/// Builds a frame from the five bytes in the order they are sent by the sensor.
+pub fn frame_from_bytes(bytes: &[u8; 5]) -> u64 {
+    bytes
+        .iter()
+        .fold(0u64, |frame, byte| (frame << 8) | *byte as u64)
+}
+
+pub fn checksum(data: u32) -> u8 {
+    data.to_be_bytes()
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Verifies the checksum of the frame and converts it into a response.
+/// Frames with a humidity above 100% are rejected as [`DHTSensorError::InvalidData`].
+pub fn decode(frame: u64, model: SensorModel) -> Result<DTHResponse, DHTSensorError> {
+    let data = (frame >> 8) as u32;
+    if checksum(data) != frame as u8 {
+        return Err(DHTSensorError::ChecksumError);
+    }
+    let (humidity, temperature) = match model {
+        SensorModel::Dht1x => (
+            dht1x::humidity((data >> 16) as u16),
+            dht1x::temperature(data as u16),
+        ),
+        SensorModel::Dht2x => (
+            dht2x::humidity((data >> 16) as u16),
+            dht2x::temperature(data as u16),
+        ),
+    };
+    if humidity <= MAX_HUMIDITY {
+        Ok(DTHResponse {
+            humidity,
+            temperature,
+        })
+    } else {
+        Err(DHTSensorError::InvalidData)
+    }
+}
+
+mod dht2x {
+    pub(super) fn humidity(data: u16) -> f32 {
+        data as f32 / 10.0
+    }
+
+    pub(super) fn temperature(data: u16) -> f32 {
+        let mut temperature = (data & 0x7FFF) as f32 / 10.0;
+        if data & 0x8000 != 0 {
+            temperature = -temperature;
+        }
+        temperature
+    }
+}
+
+mod dht1x {
+    pub(super) fn humidity(data: u16) -> f32 {
+        (data >> 8) as f32 + ((data & 0x00FF) as f32 * 0.1)
+    }
+
+    pub(super) fn temperature(data: u16) -> f32 {
+        let mut temperature = ((data & 0x7FFF) >> 8) as f32 + ((data & 0x00FF) as f32 * 0.1);
+        if data & 0x8000 != 0 {
+            temperature = -temperature;
+        }
+        temperature
+    }
+}
@@ -0,0 +1,80 @@
+//! Portable driver for any board with an embedded-hal 1.0 implementation.
+//!
+//! The pin must be able to both drive and read the data line, e.g. an open drain output with
+//! a pull-up, where setting it high releases the line to the sensor. There is no caching and no
+//! rate limiting, the caller is responsible for waiting `SensorModel::min_request_interval_secs`
+//! between reads.
+
+use crate::decode;
+use crate::diagnostics::DECISION_THRESHOLD_US;
+use crate::{DHTSensorError, DTHResponse, SensorModel};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+
+const REQUEST_HIGH_INTERVAL_US: u32 = 25;
+// The longest phase of the protocol is the ~80us start acknowledge.
+const LEVEL_TIMEOUT_US: u32 = 100;
+
+pub struct DHTSensor<P, D> {
+    pin: P,
+    delay: D,
+    model: SensorModel,
+}
+
+impl<P, D> DHTSensor<P, D>
+where
+    P: InputPin + OutputPin,
+    D: DelayNs,
+{
+    pub fn new(pin: P, delay: D, model: SensorModel) -> Self {
+        DHTSensor { pin, delay, model }
+    }
+
+    pub fn release(self) -> (P, D) {
+        (self.pin, self.delay)
+    }
+
+    pub fn read(&mut self) -> Result<DTHResponse, DHTSensorError> {
+        let frame = self.read_raw_data()?;
+        decode::decode(frame, self.model)
+    }
+
+    fn read_raw_data(&mut self) -> Result<u64, DHTSensorError> {
+        // Wake up the sensor
+        self.pin.set_low().map_err(|_| DHTSensorError::PinError)?;
+        self.delay
+            .delay_us(self.model.start_low_interval_us() as u32);
+
+        // Ask for data
+        self.pin.set_high().map_err(|_| DHTSensorError::PinError)?;
+        self.delay.delay_us(REQUEST_HIGH_INTERVAL_US);
+
+        // Wait for DHT to signal data is ready (~80us low followed by ~80us high)
+        self.wait_for_level(false)?;
+        self.wait_for_level(true)?;
+        self.wait_for_level(false)?;
+
+        let mut frame: u64 = 0;
+        for _ in 0..decode::FRAME_BITS {
+            // A "0" is high for 26-28us and a "1" for ~70us after the rising edge.
+            self.wait_for_level(true)?;
+            self.delay.delay_us(DECISION_THRESHOLD_US as u32);
+            let bit = self.pin.is_high().map_err(|_| DHTSensorError::PinError)?;
+            frame = (frame << 1) | bit as u64;
+            if bit {
+                self.wait_for_level(false)?;
+            }
+        }
+        Ok(frame)
+    }
+
+    fn wait_for_level(&mut self, high: bool) -> Result<(), DHTSensorError> {
+        for _ in 0..LEVEL_TIMEOUT_US {
+            if self.pin.is_high().map_err(|_| DHTSensorError::PinError)? == high {
+                return Ok(());
+            }
+            self.delay.delay_us(1);
+        }
+        Err(DHTSensorError::Timeout)
+    }
+}
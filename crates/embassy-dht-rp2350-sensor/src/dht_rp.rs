@@ -1,168 +1,156 @@
+use crate::decode;
+use crate::diagnostics::{self, Pulse};
+use crate::sensor::{EnvironmentalSensor, SensorError, SensorReading};
+use crate::DHTReading;
 use crate::DHTSensorError;
-use crate::DHTSensorError::InvalidData;
 use crate::DTHResponse;
-use cortex_m::interrupt::free;
-use embassy_rp::gpio::Level::{High, Low};
-use embassy_rp::gpio::{Flex, Level, Pull};
-use embassy_time::{block_for, Duration};
-
-const WAIT_FOR_READINESS_LEVEL_THRESHOLD: u32 = 80;
-const LOW_LEVEL_THRESHOLD: u32 = 55;
-const HIGH_LEVEL_THRESHOLD: u32 = 75;
-
+use crate::SensorModel;
+use crate::DEFAULT_READ_TIMEOUT;
+use embassy_rp::gpio::{Flex, Pull};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+
+const REQUEST_HIGH_INTERVAL_US: u64 = 25;
+
+// Bit-bangs the DHT protocol on a GPIO pin. The pulses are timed by waiting on pin levels and
+// taking the time once the task runs again, so every timestamp includes the interrupt and
+// executor wake-up latency. This assumes that latency stays well below the shortest phase of
+// the protocol, the ~26us high phase of a "0": a phase shorter than the latency merges with the
+// next one and the frame is rejected by the bounds in `diagnostics`. Waiting on edges instead
+// would timestamp closer to the edge, but an edge that happens before the wait is armed again
+// is lost and the read can only end in a timeout. Use the PIO driver when other interrupts or
+// tasks can delay this one for that long.
 pub struct DHTSensor<'a> {
     pin: Flex<'a>,
+    model: SensorModel,
     last_response: Option<DTHResponse>,
     last_read_time: Option<embassy_time::Instant>,
+    read_timeout: Duration,
 }
 
 impl<'a> DHTSensor<'a> {
-    pub fn new(pin: Flex<'a>) -> Self {
+    pub fn new(pin: Flex<'a>, model: SensorModel) -> Self {
         DHTSensor {
             pin,
+            model,
             last_response: None,
             last_read_time: None,
+            read_timeout: DEFAULT_READ_TIMEOUT,
         }
     }
 
-    pub fn read(&mut self) -> Result<DTHResponse, DHTSensorError> {
+    pub fn set_read_timeout(&mut self, read_timeout: Duration) {
+        self.read_timeout = read_timeout;
+    }
+
+    pub async fn read(&mut self) -> Result<DHTReading, DHTSensorError> {
         let now = embassy_time::Instant::now();
         if let Some(last_read_time) = self.last_read_time {
-            if now - last_read_time < Duration::from_secs(crate::dht::MIN_REQUEST_INTERVAL_SECS) {
+            if now - last_read_time < Duration::from_secs(self.model.min_request_interval_secs()) {
                 if let Some(response) = &self.last_response {
-                    return Ok(response.clone());
+                    return Ok(DHTReading::cached(
+                        response.clone(),
+                        now - last_read_time,
+                        None,
+                    ));
                 }
             }
         }
-        match self.read_raw_data() {
-            Ok(data) => {
-                let humidity_data: &[u16; 2] = &data[0..2].try_into().unwrap();
-                let humidity = dht::humidity(humidity_data);
-                let temperature_data: &[u16; 2] = &data[2..4].try_into().unwrap();
-                let temperature = dht::temperature(temperature_data);
-                if humidity <= 100.0 {
-                    let response = DTHResponse {
-                        humidity,
-                        temperature,
-                    };
-                    self.last_response = Some(response.clone());
-                    self.last_read_time = Some(embassy_time::Instant::now());
-                    Ok(response)
-                } else {
-                    if let Some(response) = &self.last_response {
-                        Ok(response.clone())
-                    } else {
-                        Err(InvalidData)
-                    }
-                }
-            }
-            Err(e) => {
-                if let Some(response) = &self.last_response {
-                    Ok(response.clone())
-                } else {
-                    Err(e)
-                }
+        match self
+            .read_raw_data()
+            .await
+            .and_then(|frame| decode::decode(frame, self.model))
+        {
+            Ok(response) => {
+                self.last_response = Some(response.clone());
+                self.last_read_time = Some(embassy_time::Instant::now());
+                Ok(DHTReading::fresh(response))
             }
+            Err(e) => self.cached_or(e),
         }
     }
 
-    fn read_raw_data(&mut self) -> Result<[u16; 5], DHTSensorError> {
-        let mut data: [u16; 5] = [0; 5];
-        let mut all_bits_cycles: [u32; 80] = [0; 80];
-
-        free(|_| {
-            // Wake up the sensor
-            self.pin.set_as_output();
-            self.pin.set_low();
-            block_for(Duration::from_micros(crate::dht::START_LOW_INTERVAL_US));
-
-            // Ask for data
-            self.pin.set_high();
-            block_for(Duration::from_micros(25u64));
-
-            self.pin.set_as_input();
-            self.pin.set_pull(Pull::Up);
-            block_for(Duration::from_micros(55u64));
-
-            // Wait for DHT to signal data is ready (~80us low followed by ~80us high)
-            _ = self.wait_while_level(Low, WAIT_FOR_READINESS_LEVEL_THRESHOLD);
-            _ = self.wait_while_level(High, WAIT_FOR_READINESS_LEVEL_THRESHOLD);
-
-            for bit in (0..80).step_by(2) {
-                if let Ok(bit_cycles) = self.wait_while_level(Low, LOW_LEVEL_THRESHOLD) {
-                    all_bits_cycles[bit] = bit_cycles;
-                    if let Ok(bit_cycles) = self.wait_while_level(High, HIGH_LEVEL_THRESHOLD) {
-                        all_bits_cycles[bit + 1] = bit_cycles;
-                    }
-                }
-            }
-
-            self.pin.set_as_output();
-            self.pin.set_high();
-            block_for(Duration::from_micros(1100u64));
-        });
-
-        for i in 0..40 {
-            let low_cycles = all_bits_cycles[2 * i];
-            let high_cycles = all_bits_cycles[2 * i + 1];
-            if low_cycles < LOW_LEVEL_THRESHOLD || high_cycles < HIGH_LEVEL_THRESHOLD {
-                data[i / 8] <<= 1;
-                if high_cycles > low_cycles {
-                    data[i / 8] |= 1;
-                }
-            } else {
-                return Err(DHTSensorError::Timeout);
-            }
-        }
-        let sum = data[0] + data[1] + data[2] + data[3];
-        if data[4] == sum & 0x00FF {
-            Ok(data)
-        } else {
-            Err(DHTSensorError::ChecksumError)
+    fn cached_or(&self, e: DHTSensorError) -> Result<DHTReading, DHTSensorError> {
+        match (&self.last_response, self.last_read_time) {
+            (Some(response), Some(last_read_time)) => Ok(DHTReading::cached(
+                response.clone(),
+                embassy_time::Instant::now() - last_read_time,
+                Some(e),
+            )),
+            _ => Err(e),
         }
     }
 
-    // Wait for the pin to change from the specified level, or until the timeout is reached.
-    fn wait_while_level(&mut self, level: Level, timeout_us: u32) -> Result<u32, DHTSensorError> {
-        let mut elapsed_us = 0u32;
-        while self.pin.get_level() == level && elapsed_us < timeout_us {
-            block_for(Duration::from_micros(1u64));
-            elapsed_us += 1;
-        }
-        if elapsed_us >= timeout_us {
-            Err(DHTSensorError::Timeout)
-        } else {
-            Ok(elapsed_us)
+    async fn read_raw_data(&mut self) -> Result<u64, DHTSensorError> {
+        // Wake up the sensor
+        self.pin.set_as_output();
+        self.pin.set_low();
+        Timer::after_micros(self.model.start_low_interval_us()).await;
+
+        // Ask for data
+        self.pin.set_high();
+        Timer::after_micros(REQUEST_HIGH_INTERVAL_US).await;
+
+        self.pin.set_as_input();
+        self.pin.set_pull(Pull::Up);
+
+        let pulses = with_timeout(self.read_timeout, self.read_pulses()).await;
+
+        self.pin.set_as_output();
+        self.pin.set_high();
+
+        pulses
+            .map_err(|_| DHTSensorError::Timeout)
+            .and_then(|pulses| diagnostics::checked_frame(&pulses))
+    }
+
+    // Every bit is a low phase of ~50us followed by a high phase of 26-28us for a "0"
+    // and ~70us for a "1".
+    async fn read_pulses(&mut self) -> [Pulse; decode::FRAME_BITS] {
+        // Wait for DHT to signal data is ready (~80us low followed by ~80us high)
+        self.pin.wait_for_low().await;
+        self.pin.wait_for_high().await;
+
+        let mut pulses = [Pulse::default(); decode::FRAME_BITS];
+        let mut fell_at = self.wait_for_low().await;
+        for pulse in pulses.iter_mut() {
+            let rose_at = self.wait_for_high().await;
+            let next_fell_at = self.wait_for_low().await;
+            *pulse = Pulse {
+                low_us: micros(rose_at - fell_at),
+                high_us: micros(next_fell_at - rose_at),
+            };
+            fell_at = next_fell_at;
         }
+        pulses
     }
-}
 
-#[cfg(feature = "dht2x")]
-mod dht {
-    pub(crate) fn humidity(data: &[u16; 2]) -> f32 {
-        ((data[0] << 8) | data[1]) as f32 / 10.0
+    // The level triggered waits return at once if the pin is already at the level,
+    // so an edge that happened before the task got polled again isn't missed. The
+    // timestamp is late by the wake-up latency, see `DHTSensor`.
+    async fn wait_for_high(&mut self) -> Instant {
+        self.pin.wait_for_high().await;
+        Instant::now()
     }
 
-    pub(crate) fn temperature(data: &[u16; 2]) -> f32 {
-        let mut temperature = (((data[0] & 0x7F) << 8) | data[1]) as f32 / 10.0;
-        if data[0] & 0x80 != 0 {
-            temperature = -temperature;
-        }
-        temperature
+    async fn wait_for_low(&mut self) -> Instant {
+        self.pin.wait_for_low().await;
+        Instant::now()
     }
 }
 
-#[cfg(feature = "dht1x")]
-mod dht {
-    pub(crate) fn humidity(data: &[u16; 2]) -> f32 {
-        data[0] as f32 + ((data[1] & 0x00FF) as f32 * 0.1)
+fn micros(duration: Duration) -> u16 {
+    u16::try_from(duration.as_micros()).unwrap_or(u16::MAX)
+}
+
+impl<'a> EnvironmentalSensor for DHTSensor<'a> {
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_secs(self.model.min_request_interval_secs())
     }
 
-    pub(crate) fn temperature(data: &[u16; 2]) -> f32 {
-        let mut temperature = data[0] as f32 + ((data[1] & 0x00FF) as f32 * 0.1);
-        if data[1] & 0x8000 != 0 {
-            temperature = -temperature;
-        }
-        temperature
+    async fn read(&mut self) -> Result<SensorReading, SensorError> {
+        DHTSensor::read(self)
+            .await
+            .map(SensorReading::from)
+            .map_err(SensorError::from)
     }
 }
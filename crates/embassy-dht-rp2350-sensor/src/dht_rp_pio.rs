@@ -1,153 +1,279 @@
-use crate::dht::START_LOW_INTERVAL_US;
-use crate::DHTSensorError::InvalidData;
-use crate::{DHTSensorError, DTHResponse};
+use crate::decode;
+use crate::diagnostics::{self, Pulse, SignalQuality};
+use crate::sensor::{EnvironmentalSensor, SensorError, SensorReading};
+use crate::{DHTReading, DHTSensorError, DTHResponse, SensorModel, DEFAULT_READ_TIMEOUT};
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::dma;
 use embassy_rp::pio::program::pio_file;
-use embassy_rp::pio::{Common, FifoJoin, Instance, Pin, StateMachine};
-use embassy_time::Duration;
+use embassy_rp::pio::{Common, FifoJoin, Instance, LoadedProgram, Pin, StateMachine};
+use embassy_time::{with_timeout, Duration};
 use fixed::prelude::ToFixed;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PioMode {
+    // Samples every bit at a fixed point after its rising edge, see dht22.pio.
+    Sampling,
+    // Measures the length of every low and high phase, see dht22_pulse_width.pio.
+    PulseWidth,
+}
+
+impl PioMode {
+    // The delays in the programs are counted in cycles of this clock.
+    const fn clock_hz(&self) -> u32 {
+        match self {
+            Self::Sampling => 300_000,     // 3.33us per cycle
+            Self::PulseWidth => 2_000_000, // 0.5us per cycle
+        }
+    }
+}
+
+// How the words of a frame get out of the RX FIFO.
+enum Capture<'a> {
+    Sampling,
+    // The 41 words of a frame don't fit into the RX FIFO and the state machine stalls
+    // once it is full, the DMA channel keeps up with the sensor where the executor may not.
+    PulseWidth(dma::Channel<'a>),
+}
+
+impl Capture<'_> {
+    fn mode(&self) -> PioMode {
+        match self {
+            Self::Sampling => PioMode::Sampling,
+            Self::PulseWidth(_) => PioMode::PulseWidth,
+        }
+    }
+}
+
+// A program is loaded once per PIO block and shared by up to four sensors,
+// one per state machine.
+pub struct DHTPioProgram<'a, PIO: Instance> {
+    program: LoadedProgram<'a, PIO>,
+    mode: PioMode,
+}
+
+impl<'a, PIO: Instance> DHTPioProgram<'a, PIO> {
+    pub fn load(pio: &mut Common<'a, PIO>) -> Self {
+        let prg = pio_file!("src/dht22.pio");
+        DHTPioProgram {
+            program: pio.load_program(&prg.program),
+            mode: PioMode::Sampling,
+        }
+    }
+
+    pub fn load_pulse_width(pio: &mut Common<'a, PIO>) -> Self {
+        let prg = pio_file!("src/dht22_pulse_width.pio");
+        DHTPioProgram {
+            program: pio.load_program(&prg.program),
+            mode: PioMode::PulseWidth,
+        }
+    }
+}
+
 pub struct DHTSensor<'a, PIO: Instance, const SM: usize> {
-    pio: Common<'a, PIO>,
     sm: StateMachine<'a, PIO, SM>,
-    data_pin: Pin<'a, PIO>,
+    // Owned to keep the pin assigned to the PIO block for as long as the sensor lives.
+    _data_pin: Pin<'a, PIO>,
+    model: SensorModel,
     last_response: Option<DTHResponse>,
     last_read_time: Option<embassy_time::Instant>,
-    initialized: bool,
+    read_timeout: Duration,
+    program_origin: u8,
+    capture: Capture<'a>,
+    signal_quality: Option<SignalQuality>,
 }
 
 impl<'a, PIO: Instance, const SM: usize> DHTSensor<'a, PIO, SM> {
+    // For a program loaded with `DHTPioProgram::load`.
     pub fn new(
+        program: &DHTPioProgram<'a, PIO>,
+        data_pin: Pin<'a, PIO>,
+        sm: StateMachine<'a, PIO, SM>,
+        model: SensorModel,
+    ) -> Self {
+        assert!(
+            program.mode == PioMode::Sampling,
+            "The pulse width program needs a DMA channel, use `new_pulse_width`"
+        );
+        Self::configure(program, data_pin, sm, model, Capture::Sampling)
+    }
+
+    // For a program loaded with `DHTPioProgram::load_pulse_width`.
+    pub fn new_pulse_width(
+        program: &DHTPioProgram<'a, PIO>,
         data_pin: Pin<'a, PIO>,
-        pio: Common<'a, PIO>,
         sm: StateMachine<'a, PIO, SM>,
+        dma: dma::Channel<'a>,
+        model: SensorModel,
     ) -> Self {
+        assert!(
+            program.mode == PioMode::PulseWidth,
+            "The sampling program doesn't use a DMA channel, use `new`"
+        );
+        Self::configure(program, data_pin, sm, model, Capture::PulseWidth(dma))
+    }
+
+    fn configure(
+        program: &DHTPioProgram<'a, PIO>,
+        data_pin: Pin<'a, PIO>,
+        mut sm: StateMachine<'a, PIO, SM>,
+        model: SensorModel,
+        capture: Capture<'a>,
+    ) -> Self {
+        let mut cfg = embassy_rp::pio::Config::default();
+        cfg.use_program(&program.program, &[]);
+
+        cfg.set_set_pins(&[&data_pin]);
+        cfg.set_in_pins(&[&data_pin]);
+        cfg.set_jmp_pin(&data_pin);
+
+        cfg.clock_divider = (clk_sys_freq() as f32 / program.mode.clock_hz() as f32).to_fixed();
+        cfg.fifo_join = FifoJoin::Duplex;
+
+        cfg.shift_in = embassy_rp::pio::ShiftConfig {
+            threshold: 32,
+            direction: embassy_rp::pio::ShiftDirection::Left,
+            auto_fill: true,
+        };
+        sm.set_pin_dirs(embassy_rp::pio::Direction::Out, &[&data_pin]);
+        sm.set_config(&cfg);
+
         DHTSensor {
-            pio,
             sm,
-            data_pin,
+            _data_pin: data_pin,
+            model,
             last_response: None,
             last_read_time: None,
-            initialized: false,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            program_origin: program.program.origin,
+            capture,
+            signal_quality: None,
         }
     }
 
-    async fn read_raw_data(&mut self) -> Result<[u16; 2], DHTSensorError> {
-        if !self.initialized {
-            let prg = pio_file!("src/dht22.pio");
-            let mut cfg = embassy_rp::pio::Config::default();
-
-            cfg.use_program(&self.pio.load_program(&prg.program), &[]);
-
-            cfg.set_set_pins(&[&self.data_pin]);
-            cfg.set_in_pins(&[&self.data_pin]);
-            cfg.set_jmp_pin(&self.data_pin);
-
-            cfg.clock_divider = 416.666_66_f32.to_fixed(); // 300KHz at 125 MHz system clock
-            cfg.fifo_join = FifoJoin::Duplex;
-
-            cfg.shift_in = embassy_rp::pio::ShiftConfig {
-                threshold: 32,
-                direction: embassy_rp::pio::ShiftDirection::Left,
-                auto_fill: true,
-            };
-            self.sm
-                .set_pin_dirs(embassy_rp::pio::Direction::Out, &[&self.data_pin]);
-            self.sm.set_config(&cfg);
-            self.initialized = true;
-        }
+    pub fn set_read_timeout(&mut self, read_timeout: Duration) {
+        self.read_timeout = read_timeout;
+    }
 
+    // Only available in `PioMode::PulseWidth`, for the last transaction with the sensor.
+    pub fn signal_quality(&self) -> Option<&SignalQuality> {
+        self.signal_quality.as_ref()
+    }
+
+    async fn read_raw_data(&mut self) -> Result<u64, DHTSensorError> {
+        let mode = self.capture.mode();
         self.sm.set_enable(true);
-        self.sm
-            .tx()
-            .push((START_LOW_INTERVAL_US as f32 * 0.333) as u32); // 1 cycle = 3.33us at 300KHz
-        let data = self.sm.rx().wait_pull().await;
-        let checksum_data = self.sm.rx().wait_pull().await as u8;
-        let humidity_data = (data >> 16) as u16;
-        let temperature_data = (data & 0xffff) as u16;
+        self.sm.tx().push(start_low_cycles(
+            self.model.start_low_interval_us(),
+            mode.clock_hz(),
+        ));
+        let rx = self.sm.rx();
+        let capture = &mut self.capture;
+        let frame = with_timeout(self.read_timeout, async {
+            match capture {
+                Capture::Sampling => {
+                    let data = rx.wait_pull().await;
+                    let checksum = rx.wait_pull().await as u8;
+                    (Ok(decode::frame(data, checksum)), None)
+                }
+                Capture::PulseWidth(dma) => {
+                    let mut words = [0u32; decode::FRAME_BITS + 1];
+                    rx.dma_pull(dma, &mut words, false).await;
+                    // The first word is the start acknowledge of the sensor.
+                    let pulses: [Pulse; decode::FRAME_BITS] =
+                        core::array::from_fn(|i| Pulse::from_pio_word(words[i + 1]));
+                    (
+                        diagnostics::checked_frame(&pulses),
+                        Some(diagnostics::signal_quality(&pulses)),
+                    )
+                }
+            }
+        })
+        .await;
         self.sm.set_enable(false);
 
-        // Calculate checksum
-        let humidity_bytes: [u8; 2] = humidity_data.to_le_bytes();
-        let temperature_bytes: [u8; 2] = temperature_data.to_le_bytes();
-        let checksum = ((humidity_bytes[0] as u16
-            + humidity_bytes[1] as u16
-            + temperature_bytes[0] as u16
-            + temperature_bytes[1] as u16)
-            & 0xff) as u8;
-        if checksum_data == checksum {
-            Ok([humidity_data, temperature_data])
-        } else {
-            Err(DHTSensorError::ChecksumError)
+        match frame {
+            Ok((frame, signal_quality)) => {
+                // The pulse width program keeps measuring after the frame.
+                if mode == PioMode::PulseWidth {
+                    self.recover_state_machine();
+                }
+                self.signal_quality = signal_quality;
+                frame
+            }
+            Err(_) => {
+                self.recover_state_machine();
+                self.signal_quality = None;
+                Err(DHTSensorError::Timeout)
+            }
+        }
+    }
+
+    // The state machine is stuck somewhere in the middle of the program when the sensor
+    // doesn't answer. Drop whatever was shifted in so far and start over at `pull block`.
+    fn recover_state_machine(&mut self) {
+        self.sm.clear_fifos();
+        self.sm.restart();
+        unsafe {
+            self.sm.exec_jmp(self.program_origin);
         }
     }
 
-    pub async fn read(&mut self) -> Result<DTHResponse, DHTSensorError> {
+    pub async fn read(&mut self) -> Result<DHTReading, DHTSensorError> {
         let now = embassy_time::Instant::now();
         if let Some(last_read_time) = self.last_read_time {
-            if now - last_read_time < Duration::from_secs(crate::dht::MIN_REQUEST_INTERVAL_SECS) {
+            if now - last_read_time < Duration::from_secs(self.model.min_request_interval_secs()) {
                 if let Some(response) = &self.last_response {
-                    return Ok(response.clone());
+                    return Ok(DHTReading::cached(
+                        response.clone(),
+                        now - last_read_time,
+                        None,
+                    ));
                 }
             }
-        } else if now.as_secs() < crate::dht::MIN_REQUEST_INTERVAL_SECS {
+        } else if now.as_secs() < self.model.min_request_interval_secs() {
             return Err(DHTSensorError::NoData);
         }
 
-        match self.read_raw_data().await {
-            Ok(data) => {
-                let humidity = dht::humidity(&data[0]);
-                let temperature = dht::temperature(&data[1]);
-                if humidity <= 100.0 {
-                    let response = DTHResponse {
-                        humidity,
-                        temperature,
-                    };
-                    self.last_response = Some(response.clone());
-                    self.last_read_time = Some(embassy_time::Instant::now());
-                    Ok(response)
-                } else if let Some(response) = &self.last_response {
-                    Ok(response.clone())
-                } else {
-                    Err(InvalidData)
-                }
-            }
-            Err(e) => {
-                if let Some(response) = &self.last_response {
-                    Ok(response.clone())
-                } else {
-                    Err(e)
-                }
+        match self
+            .read_raw_data()
+            .await
+            .and_then(|frame| decode::decode(frame, self.model))
+        {
+            Ok(response) => {
+                self.last_response = Some(response.clone());
+                self.last_read_time = Some(embassy_time::Instant::now());
+                Ok(DHTReading::fresh(response))
             }
+            Err(e) => self.cached_or(e),
         }
     }
-}
-
-#[cfg(feature = "dht2x")]
-mod dht {
-    pub(crate) fn humidity(data: &u16) -> f32 {
-        *data as f32 / 10.0
-    }
 
-    pub(crate) fn temperature(data: &u16) -> f32 {
-        let mut temperature = (data & 0x7FFF) as f32 / 10.0;
-        if data & 0x8000 != 0 {
-            temperature = -temperature;
+    fn cached_or(&self, e: DHTSensorError) -> Result<DHTReading, DHTSensorError> {
+        match (&self.last_response, self.last_read_time) {
+            (Some(response), Some(last_read_time)) => Ok(DHTReading::cached(
+                response.clone(),
+                embassy_time::Instant::now() - last_read_time,
+                Some(e),
+            )),
+            _ => Err(e),
         }
-        temperature
     }
 }
 
-#[cfg(feature = "dht1x")]
-mod dht {
-    pub(crate) fn humidity(data: &u16) -> f32 {
-        (*data >> 8) as f32 + ((*data & 0x00FF) as f32 * 0.1)
+impl<'a, PIO: Instance, const SM: usize> EnvironmentalSensor for DHTSensor<'a, PIO, SM> {
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_secs(self.model.min_request_interval_secs())
     }
 
-    pub(crate) fn temperature(data: &u16) -> f32 {
-        let mut temperature = ((data & 0x7FFF) >> 8) as f32 + ((*data & 0x00FF) as f32 * 0.1);
-        if data & 0x8000 != 0 {
-            temperature = -temperature;
-        }
-        temperature
+    async fn read(&mut self) -> Result<SensorReading, SensorError> {
+        DHTSensor::read(self)
+            .await
+            .map(SensorReading::from)
+            .map_err(SensorError::from)
     }
 }
+
+// The start pulse is timed by a `jmp x--` loop that takes one PIO cycle per iteration.
+fn start_low_cycles(start_low_interval_us: u64, clock_hz: u32) -> u32 {
+    (start_low_interval_us * clock_hz as u64 / 1_000_000) as u32
+}
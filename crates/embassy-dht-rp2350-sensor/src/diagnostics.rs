@@ -0,0 +1,78 @@
+//! Hardware independent analysis of the pulse widths measured by `dht22_pulse_width.pio`.
+//!
+//! Every bit starts with a low phase of about 50us followed by a high phase of 26-28us for a "0"
+//! and about 70us for a "1". How far the high phases are from the decision threshold tells how
+//! much timing margin is left, e.g. with long cables or a weak pull-up.
+
+use crate::decode::FRAME_BITS;
+use crate::DHTSensorError;
+
+// The sampling program reads the pin 43.3us after the rising edge, use the same point to
+// tell a "0" from a "1".
+pub const DECISION_THRESHOLD_US: u16 = 43;
+// Phases outside of these bounds don't belong to a bit, e.g. when an edge was missed and two
+// phases merged. They leave room for the latency of timestamping the edges in software.
+pub const MIN_LOW_US: u16 = 30;
+pub const MAX_LOW_US: u16 = 90;
+pub const MIN_HIGH_US: u16 = 15;
+pub const MAX_HIGH_US: u16 = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pulse {
+    pub low_us: u16,
+    pub high_us: u16,
+}
+
+impl Pulse {
+    // Both phases are counted down from 0xFFFF, the low phase in the upper half of the word.
+    pub fn from_pio_word(word: u32) -> Self {
+        Pulse {
+            low_us: !(word >> 16) as u16,
+            high_us: !word as u16,
+        }
+    }
+
+    pub fn bit(&self) -> bool {
+        self.high_us > DECISION_THRESHOLD_US
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (MIN_LOW_US..=MAX_LOW_US).contains(&self.low_us)
+            && (MIN_HIGH_US..=MAX_HIGH_US).contains(&self.high_us)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignalQuality {
+    pub min_high_us: u16,
+    pub max_high_us: u16,
+    // The distance of the high phase closest to the decision threshold.
+    pub margin_us: u16,
+}
+
+pub fn frame(pulses: &[Pulse; FRAME_BITS]) -> u64 {
+    pulses
+        .iter()
+        .fold(0u64, |frame, pulse| (frame << 1) | pulse.bit() as u64)
+}
+
+// Rejects the frame if any of its phases is out of bounds.
+pub fn checked_frame(pulses: &[Pulse; FRAME_BITS]) -> Result<u64, DHTSensorError> {
+    if pulses.iter().all(Pulse::is_valid) {
+        Ok(frame(pulses))
+    } else {
+        Err(DHTSensorError::InvalidData)
+    }
+}
+
+pub fn signal_quality(pulses: &[Pulse; FRAME_BITS]) -> SignalQuality {
+    let high_us = pulses.iter().map(|pulse| pulse.high_us);
+    SignalQuality {
+        min_high_us: high_us.clone().min().unwrap_or_default(),
+        max_high_us: high_us.clone().max().unwrap_or_default(),
+        margin_us: high_us
+            .map(|high_us| high_us.abs_diff(DECISION_THRESHOLD_US))
+            .min()
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,93 @@
+//! Driver for DS18B20 temperature probes, any number of them sharing one 1-Wire bus.
+
+use crate::onewire::{self, OneWireBus, RomSearch};
+use crate::DHTSensorError;
+use embedded_hal_async::delay::DelayNs;
+
+pub const FAMILY_CODE: u8 = 0x28;
+pub const MAX_PROBES: usize = 8;
+
+const CONVERT_T: u8 = 0x44;
+const READ_SCRATCHPAD: u8 = 0xBE;
+// Maximum conversion time at the default 12 bit resolution.
+const CONVERSION_US: u32 = 750_000;
+// The scratchpad holds 85 °C until the first conversion, a probe still reporting it after a
+// conversion lost its power in between.
+const POWER_ON_RESET: i16 = 0x0550;
+
+/// Converts the scratchpad of a finished conversion, the temperature is a signed fixed point
+/// number with 4 fractional bits in its first two bytes.
+pub fn temperature(scratchpad: &[u8; 9]) -> Result<f32, DHTSensorError> {
+    // A bus held low reads as zeros, which passes the CRC.
+    if scratchpad.iter().all(|&byte| byte == 0) {
+        return Err(DHTSensorError::BusError);
+    }
+    if onewire::crc8(scratchpad) != 0 {
+        return Err(DHTSensorError::ChecksumError);
+    }
+    let raw_temperature = i16::from_le_bytes([scratchpad[0], scratchpad[1]]);
+    if raw_temperature == POWER_ON_RESET {
+        return Err(DHTSensorError::NoData);
+    }
+    Ok(raw_temperature as f32 / 16.0)
+}
+
+pub struct Ds18b20<B, D> {
+    bus: B,
+    delay: D,
+    probes: [u64; MAX_PROBES],
+    probe_count: usize,
+}
+
+impl<B: OneWireBus, D: DelayNs> Ds18b20<B, D> {
+    pub fn new(bus: B, delay: D) -> Self {
+        Ds18b20 {
+            bus,
+            delay,
+            probes: [0; MAX_PROBES],
+            probe_count: 0,
+        }
+    }
+
+    pub fn release(self) -> (B, D) {
+        (self.bus, self.delay)
+    }
+
+    // The ROMs of the probes found by the last `discover`, in the order of the search.
+    pub fn probes(&self) -> &[u64] {
+        &self.probes[..self.probe_count]
+    }
+
+    // Searches the bus for probes, other 1-Wire devices and probes beyond `MAX_PROBES` are
+    // ignored.
+    pub async fn discover(&mut self) -> Result<&[u64], DHTSensorError> {
+        self.probe_count = 0;
+        let mut search = RomSearch::new();
+        while let Some(rom) = search.next(&mut self.bus).await? {
+            if onewire::family_code(rom) == FAMILY_CODE && self.probe_count < MAX_PROBES {
+                self.probes[self.probe_count] = rom;
+                self.probe_count += 1;
+            }
+        }
+        Ok(self.probes())
+    }
+
+    // Starts a conversion on all probes at once and waits for it to finish.
+    pub async fn convert(&mut self) -> Result<(), DHTSensorError> {
+        self.bus.skip_rom().await?;
+        self.bus.write_byte(CONVERT_T).await;
+        self.delay.delay_us(CONVERSION_US).await;
+        Ok(())
+    }
+
+    // Reads the result of the last conversion of a single probe.
+    pub async fn read_temperature(&mut self, rom: u64) -> Result<f32, DHTSensorError> {
+        self.bus.select(rom).await?;
+        self.bus.write_byte(READ_SCRATCHPAD).await;
+        let mut scratchpad = [0u8; 9];
+        for byte in scratchpad.iter_mut() {
+            *byte = self.bus.read_byte().await;
+        }
+        temperature(&scratchpad)
+    }
+}
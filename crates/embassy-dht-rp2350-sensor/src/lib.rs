@@ -1,65 +1,145 @@
 #![no_std]
 #![no_main]
 
-#[cfg(not(any(feature = "dht1x", feature = "dht2x")))]
-compile_error!("You must select a DHT sensor model with a feature flag: dht1x or dht2x");
-
-#[cfg(all(feature = "dht1x", feature = "dht2x"))]
-compile_error!("You must select only one DHT sensor model with a feature flag: dht1x or dht2x");
-
-#[cfg(not(any(feature = "rp_no_pio", feature = "rp_pio")))]
-compile_error!("You must select a DHT sensor model with a feature flag: rp_no_pio or rp_pio");
-
-#[cfg(all(feature = "rp_no_pio", feature = "rp_pio"))]
+#[cfg(not(any(
+    feature = "rp_no_pio",
+    feature = "rp_pio",
+    feature = "hal",
+    feature = "sht4x",
+    feature = "bme280",
+    feature = "ds18b20",
+    feature = "scd4x",
+    feature = "pms5003"
+)))]
 compile_error!(
-    "You must select only one DHT sensor model with a feature flag: rp_no_pio or rp_pio"
+    "You must select a sensor driver with a feature flag: rp_no_pio, rp_pio, hal, sht4x, bme280, ds18b20, scd4x or pms5003"
 );
 
-#[cfg(all(
-    feature = "rp235xa",
-    all(feature = "rp_no_pio", not(feature = "rp_pio"))
-))]
-mod dht_rp;
+// Both drivers can be enabled together, e.g. to fall back to the GPIO driver when no PIO state
+// machine is free. `DHTSensor` then refers to the PIO driver.
+#[cfg(all(feature = "rp235xa", feature = "rp_no_pio"))]
+pub mod dht_rp;
 
-#[cfg(all(
-    feature = "rp235xa",
-    all(feature = "rp_no_pio", not(feature = "rp_pio"))
-))]
+#[cfg(all(feature = "rp235xa", feature = "rp_no_pio", not(feature = "rp_pio")))]
 pub use dht_rp::DHTSensor;
 
-#[cfg(all(
-    feature = "rp235xa",
-    all(feature = "rp_pio", not(feature = "rp_no_pio"))
-))]
+#[cfg(all(feature = "rp235xa", feature = "rp_pio"))]
 mod dht_rp_pio;
-#[cfg(all(
-    feature = "rp235xa",
-    all(feature = "rp_pio", not(feature = "rp_no_pio"))
-))]
-pub use dht_rp_pio::DHTSensor;
+#[cfg(all(feature = "rp235xa", feature = "rp_pio"))]
+pub use dht_rp_pio::{DHTPioProgram, DHTSensor, PioMode};
+
+#[cfg(feature = "hal")]
+pub mod dht_hal;
+
+#[cfg(any(feature = "sht4x", feature = "scd4x"))]
+pub mod sensirion;
+
+#[cfg(feature = "sht4x")]
+pub mod sht4x;
+
+#[cfg(feature = "scd4x")]
+pub mod scd4x;
+
+#[cfg(feature = "pms5003")]
+pub mod pms5003;
+
+#[cfg(feature = "bme280")]
+pub mod bme280;
+
+#[cfg(feature = "ds18b20")]
+pub mod ds18b20;
+#[cfg(feature = "ds18b20")]
+pub mod onewire;
+#[cfg(all(feature = "rp235xa", feature = "ds18b20"))]
+mod onewire_rp_pio;
+#[cfg(all(feature = "rp235xa", feature = "ds18b20"))]
+pub use onewire_rp_pio::{OneWirePio, OneWirePioProgram};
+
+pub mod decode;
+pub mod diagnostics;
+pub mod sensor;
 
-#[derive(Clone)]
+pub use sensor::{EnvironmentalSensor, SensorError, SensorReading, SensorValues};
+
+use embassy_time::Duration;
+
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct DTHResponse {
     pub humidity: f32,
     pub temperature: f32,
 }
 
-#[derive(Debug, Clone)]
+// A reading is cached when it was served from the last successful read, either because the
+// minimum request interval hasn't passed yet or because the sensor read failed with `error`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DHTReading {
+    pub response: DTHResponse,
+    pub age: Duration,
+    pub is_cached: bool,
+    pub error: Option<DHTSensorError>,
+}
+
+impl DHTReading {
+    pub fn fresh(response: DTHResponse) -> Self {
+        Self {
+            response,
+            age: Duration::MIN,
+            is_cached: false,
+            error: None,
+        }
+    }
+
+    pub fn cached(response: DTHResponse, age: Duration, error: Option<DHTSensorError>) -> Self {
+        Self {
+            response,
+            age,
+            is_cached: true,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum DHTSensorError {
     NoData,
     ChecksumError,
     InvalidData,
     Timeout,
+    PinError,
+    BusError,
 }
 
-#[cfg(feature = "dht2x")]
-mod dht {
-    pub(crate) const MIN_REQUEST_INTERVAL_SECS: u64 = 2;
-    pub(crate) const START_LOW_INTERVAL_US: u64 = 1_100; // 1ms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SensorModel {
+    Dht1x,
+    Dht2x,
+}
+
+impl SensorModel {
+    pub const fn min_request_interval_secs(&self) -> u64 {
+        match self {
+            Self::Dht1x => 1,
+            Self::Dht2x => 2,
+        }
+    }
+
+    pub const fn start_low_interval_us(&self) -> u64 {
+        match self {
+            Self::Dht1x => 18_000, // 18ms
+            Self::Dht2x => 1_100,  // 1ms
+        }
+    }
 }
 
-#[cfg(feature = "dht1x")]
-mod dht {
-    pub(crate) const MIN_REQUEST_INTERVAL_SECS: u64 = 1;
-    pub(crate) const START_LOW_INTERVAL_US: u64 = 18_000; // 18ms
+// The dht1x and dht2x features only select the default model.
+impl Default for SensorModel {
+    fn default() -> Self {
+        if cfg!(all(feature = "dht1x", not(feature = "dht2x"))) {
+            Self::Dht1x
+        } else {
+            Self::Dht2x
+        }
+    }
 }
@@ -0,0 +1,154 @@
+//! Hardware independent part of the 1-Wire protocol: byte transfers, ROM search and the Dallas CRC.
+//!
+//! Bytes are sent least significant bit first. A ROM is the 64-bit identifier of a device,
+//! the family code in the lowest byte followed by a 48-bit serial number and a CRC.
+
+use crate::DHTSensorError;
+
+pub const SEARCH_ROM: u8 = 0xF0;
+pub const MATCH_ROM: u8 = 0x55;
+pub const SKIP_ROM: u8 = 0xCC;
+
+const ROM_BITS: u8 = 64;
+const CRC_POLYNOMIAL: u8 = 0x8C; // x^8 + x^5 + x^4 + 1, reflected
+
+#[allow(async_fn_in_trait)]
+pub trait OneWireBus {
+    // Returns whether any device answered the reset pulse with a presence pulse.
+    async fn reset(&mut self) -> bool;
+
+    // Runs a single time slot. Writing a "1" doubles as a read slot, the returned level is
+    // low when a device pulled the line low during the slot.
+    async fn touch_bit(&mut self, bit: bool) -> bool;
+
+    async fn read_bit(&mut self) -> bool {
+        self.touch_bit(true).await
+    }
+
+    async fn write_bit(&mut self, bit: bool) {
+        self.touch_bit(bit).await;
+    }
+
+    async fn read_byte(&mut self) -> u8 {
+        let mut byte = 0;
+        for i in 0..8 {
+            if self.read_bit().await {
+                byte |= 1 << i;
+            }
+        }
+        byte
+    }
+
+    async fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit((byte >> i) & 1 == 1).await;
+        }
+    }
+
+    // Resets the bus and selects a single device, e.g. before a function command.
+    async fn select(&mut self, rom: u64) -> Result<(), DHTSensorError> {
+        if !self.reset().await {
+            return Err(DHTSensorError::NoData);
+        }
+        self.write_byte(MATCH_ROM).await;
+        for byte in rom.to_le_bytes() {
+            self.write_byte(byte).await;
+        }
+        Ok(())
+    }
+
+    // Resets the bus and addresses all devices at once.
+    async fn skip_rom(&mut self) -> Result<(), DHTSensorError> {
+        if !self.reset().await {
+            return Err(DHTSensorError::NoData);
+        }
+        self.write_byte(SKIP_ROM).await;
+        Ok(())
+    }
+}
+
+/// CRC-8 of the Dallas/Maxim 1-Wire devices. The CRC over data followed by its CRC is zero.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x01 != 0 {
+                (crc >> 1) ^ CRC_POLYNOMIAL
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+pub fn family_code(rom: u64) -> u8 {
+    rom as u8
+}
+
+/// Enumerates the ROMs of all devices on the bus, one per call to [`RomSearch::next`],
+/// with the search algorithm of Maxim application note 187.
+#[derive(Default)]
+pub struct RomSearch {
+    rom: u64,
+    last_discrepancy: u8,
+    done: bool,
+}
+
+impl RomSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `Ok(None)` when all devices have been found.
+    pub async fn next<B: OneWireBus>(
+        &mut self,
+        bus: &mut B,
+    ) -> Result<Option<u64>, DHTSensorError> {
+        if self.done || !bus.reset().await {
+            self.done = true;
+            return Ok(None);
+        }
+        bus.write_byte(SEARCH_ROM).await;
+
+        // Every device sends the next bit of its ROM followed by its complement, so both
+        // are low when devices disagree on the bit.
+        let mut last_zero = 0;
+        for bit_number in 1..=ROM_BITS {
+            let bit = bus.read_bit().await;
+            let complement = bus.read_bit().await;
+            let direction = match (bit, complement) {
+                (true, true) => {
+                    // All devices dropped off the bus during the search.
+                    self.done = true;
+                    return Err(DHTSensorError::NoData);
+                }
+                (true, false) => true,
+                (false, true) => false,
+                (false, false) => {
+                    let direction = if bit_number < self.last_discrepancy {
+                        (self.rom >> (bit_number - 1)) & 1 == 1
+                    } else {
+                        bit_number == self.last_discrepancy
+                    };
+                    if !direction {
+                        last_zero = bit_number;
+                    }
+                    direction
+                }
+            };
+            // Devices with a different bit stop taking part in the search.
+            if direction {
+                self.rom |= 1 << (bit_number - 1);
+            } else {
+                self.rom &= !(1 << (bit_number - 1));
+            }
+            bus.write_bit(direction).await;
+        }
+
+        self.last_discrepancy = last_zero;
+        self.done = last_zero == 0;
+        if crc8(&self.rom.to_le_bytes()) != 0 {
+            return Err(DHTSensorError::ChecksumError);
+        }
+        Ok(Some(self.rom))
+    }
+}
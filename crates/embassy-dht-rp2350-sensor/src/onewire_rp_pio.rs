@@ -0,0 +1,82 @@
+use crate::onewire::OneWireBus;
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::gpio::Level;
+use embassy_rp::pio::program::pio_file;
+use embassy_rp::pio::{Common, Instance, LoadedProgram, Pin, StateMachine};
+use fixed::prelude::ToFixed;
+
+// The delays in onewire.pio are counted in cycles of this clock.
+const CLOCK_HZ: u32 = 1_000_000;
+
+// Loaded once per PIO block, like `DHTPioProgram`.
+pub struct OneWirePioProgram<'a, PIO: Instance> {
+    program: LoadedProgram<'a, PIO>,
+}
+
+impl<'a, PIO: Instance> OneWirePioProgram<'a, PIO> {
+    pub fn load(pio: &mut Common<'a, PIO>) -> Self {
+        let prg = pio_file!("src/onewire.pio");
+        OneWirePioProgram {
+            program: pio.load_program(&prg.program),
+        }
+    }
+}
+
+// 1-Wire master on a single pin with an external pull-up, 4.7k for the DS18B20.
+pub struct OneWirePio<'a, PIO: Instance, const SM: usize> {
+    sm: StateMachine<'a, PIO, SM>,
+    // Owned to keep the pin assigned to the PIO block for as long as the bus lives.
+    _data_pin: Pin<'a, PIO>,
+    program_origin: u8,
+}
+
+impl<'a, PIO: Instance, const SM: usize> OneWirePio<'a, PIO, SM> {
+    pub fn new(
+        program: &OneWirePioProgram<'a, PIO>,
+        data_pin: Pin<'a, PIO>,
+        mut sm: StateMachine<'a, PIO, SM>,
+    ) -> Self {
+        let mut cfg = embassy_rp::pio::Config::default();
+        cfg.use_program(&program.program, &[&data_pin]);
+        cfg.set_in_pins(&[&data_pin]);
+
+        cfg.clock_divider = (clk_sys_freq() as f32 / CLOCK_HZ as f32).to_fixed();
+        cfg.shift_out = embassy_rp::pio::ShiftConfig {
+            threshold: 32,
+            direction: embassy_rp::pio::ShiftDirection::Right,
+            auto_fill: false,
+        };
+        cfg.shift_in = embassy_rp::pio::ShiftConfig {
+            threshold: 32,
+            direction: embassy_rp::pio::ShiftDirection::Left,
+            auto_fill: false,
+        };
+        // The line is only ever driven low, side-set switches between output and input.
+        sm.set_pins(Level::Low, &[&data_pin]);
+        sm.set_pin_dirs(embassy_rp::pio::Direction::In, &[&data_pin]);
+        sm.set_config(&cfg);
+        sm.set_enable(true);
+
+        OneWirePio {
+            sm,
+            _data_pin: data_pin,
+            program_origin: program.program.origin,
+        }
+    }
+}
+
+impl<'a, PIO: Instance, const SM: usize> OneWireBus for OneWirePio<'a, PIO, SM> {
+    async fn reset(&mut self) -> bool {
+        // Drop the presence result of a previous reset and start over at the reset pulse.
+        self.sm.clear_fifos();
+        unsafe {
+            self.sm.exec_jmp(self.program_origin);
+        }
+        self.sm.rx().wait_pull().await == 0
+    }
+
+    async fn touch_bit(&mut self, bit: bool) -> bool {
+        self.sm.tx().wait_push(bit as u32).await;
+        self.sm.rx().wait_pull().await != 0
+    }
+}
@@ -0,0 +1,279 @@
+//! Plantower PMS5003 particulate matter sensor on a UART at 9600 baud.
+//!
+//! The sensor sends 32 byte frames: the start characters 0x42 0x4D, the frame length, 13 big
+//! endian data words and a check code, the sum of all preceding bytes. In active mode a frame
+//! is sent every second, in passive mode only on request. Commands are answered with a
+//! shorter 8 byte frame.
+
+use crate::sensor::{EnvironmentalSensor, SensorError, SensorReading, SensorValues};
+use crate::DHTSensorError;
+use embassy_time::{with_timeout, Duration};
+use embedded_io_async::{Read, ReadReady, Write};
+
+pub const FRAME_LEN: usize = 32;
+
+const START_CHARACTERS: [u8; 2] = [0x42, 0x4D];
+const DATA_FRAME_LENGTH: u16 = 28;
+const COMMAND_FRAME_LENGTH: u16 = 4;
+// In passive mode the sensor answers within a second, in active mode it sends a frame every
+// 200-2300ms depending on how fast the concentration changes.
+const READ_TIMEOUT: Duration = Duration::from_millis(2_500);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Active,
+    Passive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    SetMode(Mode),
+    // Requests a frame in passive mode.
+    Read,
+    Sleep,
+    WakeUp,
+}
+
+impl Command {
+    pub fn frame(&self) -> [u8; 7] {
+        let (command, data) = match self {
+            Self::SetMode(Mode::Passive) => (0xE1, 0x00),
+            Self::SetMode(Mode::Active) => (0xE1, 0x01),
+            Self::Read => (0xE2, 0x00),
+            Self::Sleep => (0xE4, 0x00),
+            Self::WakeUp => (0xE4, 0x01),
+        };
+        let mut frame = [
+            START_CHARACTERS[0],
+            START_CHARACTERS[1],
+            command,
+            0x00,
+            data,
+            0,
+            0,
+        ];
+        let [high, low] = check_code(&frame[..5]).to_be_bytes();
+        frame[5] = high;
+        frame[6] = low;
+        frame
+    }
+}
+
+/// Mass concentrations in µg/m³ under atmospheric environment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParticulateMatter {
+    pub pm1_0: u16,
+    pub pm2_5: u16,
+    pub pm10: u16,
+}
+
+impl From<ParticulateMatter> for SensorValues {
+    fn from(particulate_matter: ParticulateMatter) -> Self {
+        Self {
+            pm1_0: Some(particulate_matter.pm1_0),
+            pm2_5: Some(particulate_matter.pm2_5),
+            pm10: Some(particulate_matter.pm10),
+            ..Default::default()
+        }
+    }
+}
+
+fn check_code(bytes: &[u8]) -> u16 {
+    bytes
+        .iter()
+        .fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16))
+}
+
+/// Verifies the start characters, the frame length and the check code of a data frame.
+pub fn decode(frame: &[u8; FRAME_LEN]) -> Result<ParticulateMatter, DHTSensorError> {
+    let word = |i: usize| u16::from_be_bytes([frame[2 * i], frame[2 * i + 1]]);
+    if frame[..2] != START_CHARACTERS || word(1) != DATA_FRAME_LENGTH {
+        return Err(DHTSensorError::InvalidData);
+    }
+    if check_code(&frame[..FRAME_LEN - 2]) != word(15) {
+        return Err(DHTSensorError::ChecksumError);
+    }
+    // Words 2-4 are the concentrations for standard particles (CF=1).
+    Ok(ParticulateMatter {
+        pm1_0: word(5),
+        pm2_5: word(6),
+        pm10: word(7),
+    })
+}
+
+/// Finds data frames in a byte stream, e.g. after connecting in the middle of a frame.
+/// Command answers are skipped.
+#[derive(Default)]
+pub struct FrameParser {
+    buffer: [u8; FRAME_LEN],
+    len: usize,
+}
+
+impl FrameParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the decoded frame once its last byte has been pushed.
+    pub fn push(&mut self, byte: u8) -> Option<Result<ParticulateMatter, DHTSensorError>> {
+        if self.len < START_CHARACTERS.len() && byte != START_CHARACTERS[self.len] {
+            // A lost byte may have put the start of the next frame here.
+            self.len = 0;
+            if byte == START_CHARACTERS[0] {
+                self.buffer[0] = byte;
+                self.len = 1;
+            }
+            return None;
+        }
+        self.buffer[self.len] = byte;
+        self.len += 1;
+
+        if self.len < 4 {
+            return None;
+        }
+        let frame_length = u16::from_be_bytes([self.buffer[2], self.buffer[3]]);
+        match frame_length {
+            DATA_FRAME_LENGTH if self.len == FRAME_LEN => {
+                let result = decode(&self.buffer);
+                if result.is_err() {
+                    self.resync();
+                } else {
+                    self.len = 0;
+                }
+                Some(result)
+            }
+            DATA_FRAME_LENGTH => None,
+            COMMAND_FRAME_LENGTH if self.len < 4 + COMMAND_FRAME_LENGTH as usize => None,
+            _ => {
+                self.len = 0;
+                None
+            }
+        }
+    }
+
+    // After a lost byte the start of the next frame ended up at the end of the buffer.
+    fn resync(&mut self) {
+        let start = (1..FRAME_LEN).find(|&i| {
+            self.buffer[i] == START_CHARACTERS[0]
+                && (i + 1 == FRAME_LEN || self.buffer[i + 1] == START_CHARACTERS[1])
+        });
+        self.len = match start {
+            Some(start) => {
+                self.buffer.copy_within(start.., 0);
+                FRAME_LEN - start
+            }
+            None => 0,
+        };
+    }
+}
+
+pub struct Pms5003<U> {
+    uart: U,
+    parser: FrameParser,
+    mode: Mode,
+}
+
+impl<U: Read + ReadReady + Write> Pms5003<U> {
+    // The sensor starts in active mode after power up.
+    pub fn new(uart: U) -> Self {
+        Pms5003 {
+            uart,
+            parser: FrameParser::new(),
+            mode: Mode::Active,
+        }
+    }
+
+    pub fn release(self) -> U {
+        self.uart
+    }
+
+    pub async fn set_mode(&mut self, mode: Mode) -> Result<(), DHTSensorError> {
+        self.send(Command::SetMode(mode)).await?;
+        self.mode = mode;
+        Ok(())
+    }
+
+    // The fan and the laser are turned off while sleeping, the first readings after waking up
+    // are only stable after 30 seconds.
+    pub async fn sleep(&mut self) -> Result<(), DHTSensorError> {
+        self.send(Command::Sleep).await
+    }
+
+    pub async fn wake_up(&mut self) -> Result<(), DHTSensorError> {
+        self.send(Command::WakeUp).await
+    }
+
+    // In active mode the next frame sent by the sensor, in passive mode a frame is requested.
+    // Frames received since the last read are dropped, they may be several seconds old.
+    // A corrupted frame is only reported if no valid one follows before the timeout.
+    pub async fn read(&mut self) -> Result<ParticulateMatter, DHTSensorError> {
+        self.drain().await?;
+        if self.mode == Mode::Passive {
+            self.send(Command::Read).await?;
+        }
+        let mut error = None;
+        with_timeout(READ_TIMEOUT, self.read_frame(&mut error))
+            .await
+            .map_err(|_| error.unwrap_or(DHTSensorError::Timeout))?
+    }
+
+    async fn drain(&mut self) -> Result<(), DHTSensorError> {
+        let mut buffer = [0u8; FRAME_LEN];
+        while self
+            .uart
+            .read_ready()
+            .map_err(|_| DHTSensorError::BusError)?
+        {
+            self.uart
+                .read(&mut buffer)
+                .await
+                .map_err(|_| DHTSensorError::BusError)?;
+        }
+        self.parser = FrameParser::new();
+        Ok(())
+    }
+
+    // Keeps the error of the last corrupted frame in `error`, the parser resyncs to the next one.
+    async fn read_frame(
+        &mut self,
+        error: &mut Option<DHTSensorError>,
+    ) -> Result<ParticulateMatter, DHTSensorError> {
+        let mut buffer = [0u8; FRAME_LEN];
+        loop {
+            let count = self
+                .uart
+                .read(&mut buffer)
+                .await
+                .map_err(|_| DHTSensorError::BusError)?;
+            for byte in &buffer[..count] {
+                match self.parser.push(*byte) {
+                    Some(Ok(particulate_matter)) => {
+                        // The rest of the read is the start of the next frame, which is dropped.
+                        self.parser = FrameParser::new();
+                        return Ok(particulate_matter);
+                    }
+                    Some(Err(err)) => *error = Some(err),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    async fn send(&mut self, command: Command) -> Result<(), DHTSensorError> {
+        self.uart
+            .write_all(&command.frame())
+            .await
+            .map_err(|_| DHTSensorError::BusError)
+    }
+}
+
+impl<U: Read + ReadReady + Write> EnvironmentalSensor for Pms5003<U> {
+    // The sensor updates its concentrations about once per second.
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    async fn read(&mut self) -> Result<SensorReading, SensorError> {
+        Ok(SensorReading::fresh(Pms5003::read(self).await?.into()))
+    }
+}
@@ -0,0 +1,196 @@
+//! Async driver for the Sensirion SCD4x (SCD40, SCD41) photoacoustic CO2 sensors.
+//!
+//! Commands are 16 bit and big endian, command arguments and responses are words followed by
+//! their CRC-8. The sensor measures periodically every 5 seconds once started, most
+//! configuration commands are only accepted while the periodic measurement is stopped.
+
+use crate::sensirion::{word, word_with_crc};
+use crate::sensor::{EnvironmentalSensor, SensorError, SensorReading, SensorValues};
+use crate::DHTSensorError;
+use embassy_time::Duration;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+pub const DEFAULT_ADDRESS: u8 = 0x62;
+
+const START_PERIODIC_MEASUREMENT: u16 = 0x21B1;
+const READ_MEASUREMENT: u16 = 0xEC05;
+const STOP_PERIODIC_MEASUREMENT: u16 = 0x3F86;
+const GET_DATA_READY_STATUS: u16 = 0xE4B8;
+const PERFORM_FORCED_RECALIBRATION: u16 = 0x362F;
+const SET_SENSOR_ALTITUDE: u16 = 0x2427;
+const SET_AMBIENT_PRESSURE: u16 = 0xE000;
+const GET_SERIAL_NUMBER: u16 = 0x3682;
+
+const COMMAND_US: u32 = 1_000;
+const STOP_PERIODIC_MEASUREMENT_US: u32 = 500_000;
+const FORCED_RECALIBRATION_US: u32 = 400_000;
+const MEASUREMENT_INTERVAL_US: u32 = 5_000_000;
+
+const FORCED_RECALIBRATION_FAILED: u16 = 0xFFFF;
+
+pub struct Scd4x<I, D> {
+    i2c: I,
+    delay: D,
+    address: u8,
+    measuring: bool,
+}
+
+impl<I: I2c, D: DelayNs> Scd4x<I, D> {
+    pub fn new(i2c: I, delay: D) -> Self {
+        Scd4x {
+            i2c,
+            delay,
+            address: DEFAULT_ADDRESS,
+            measuring: false,
+        }
+    }
+
+    // Creates the driver and starts the periodic measurement, the first result is available
+    // after one measurement interval. A failed start is retried by the next read.
+    pub async fn start(i2c: I, delay: D) -> Self {
+        let mut scd4x = Self::new(i2c, delay);
+        // The error is reported by the next read as well.
+        let _ = scd4x.start_periodic_measurement().await;
+        scd4x
+    }
+
+    pub fn release(self) -> (I, D) {
+        (self.i2c, self.delay)
+    }
+
+    pub async fn start_periodic_measurement(&mut self) -> Result<(), DHTSensorError> {
+        self.write(START_PERIODIC_MEASUREMENT, None).await?;
+        self.measuring = true;
+        Ok(())
+    }
+
+    pub async fn stop_periodic_measurement(&mut self) -> Result<(), DHTSensorError> {
+        self.write(STOP_PERIODIC_MEASUREMENT, None).await?;
+        self.delay.delay_us(STOP_PERIODIC_MEASUREMENT_US).await;
+        self.measuring = false;
+        Ok(())
+    }
+
+    pub async fn data_ready(&mut self) -> Result<bool, DHTSensorError> {
+        let [status] = self.read(GET_DATA_READY_STATUS).await?;
+        Ok(status & 0x07FF != 0)
+    }
+
+    // Reads the latest periodic measurement, only available once per measurement interval.
+    pub async fn read_measurement(&mut self) -> Result<SensorValues, DHTSensorError> {
+        let [co2_ppm, temperature, humidity] = self.read(READ_MEASUREMENT).await?;
+        Ok(SensorValues {
+            temperature: Some(-45.0 + 175.0 * temperature as f32 / 65535.0),
+            humidity: Some(100.0 * humidity as f32 / 65535.0),
+            co2_ppm: Some(co2_ppm),
+            ..Default::default()
+        })
+    }
+
+    // Sets the CO2 reading to the given reference, e.g. 420 ppm after operating the sensor
+    // outdoors for at least 3 minutes. The periodic measurement is stopped for the
+    // recalibration and started again. Returns the correction applied in ppm.
+    pub async fn forced_recalibration(
+        &mut self,
+        reference_ppm: u16,
+    ) -> Result<i16, DHTSensorError> {
+        self.stop_if_measuring().await?;
+        self.write(PERFORM_FORCED_RECALIBRATION, Some(reference_ppm))
+            .await?;
+        self.delay.delay_us(FORCED_RECALIBRATION_US).await;
+        let mut buffer = [0u8; 3];
+        self.i2c
+            .read(self.address, &mut buffer)
+            .await
+            .map_err(|_| DHTSensorError::BusError)?;
+        self.start_periodic_measurement().await?;
+        match word(&buffer)? {
+            FORCED_RECALIBRATION_FAILED => Err(DHTSensorError::InvalidData),
+            correction => Ok(correction.wrapping_sub(0x8000) as i16),
+        }
+    }
+
+    // Compensates the CO2 reading for the altitude above sea level. Overridden by the ambient
+    // pressure. The periodic measurement is stopped for the setting and started again.
+    pub async fn set_altitude(&mut self, altitude_m: u16) -> Result<(), DHTSensorError> {
+        self.stop_if_measuring().await?;
+        self.write(SET_SENSOR_ALTITUDE, Some(altitude_m)).await?;
+        self.delay.delay_us(COMMAND_US).await;
+        self.start_periodic_measurement().await
+    }
+
+    // Compensates the CO2 reading for the ambient pressure, e.g. from a BME280. Can be called
+    // during periodic measurement.
+    pub async fn set_ambient_pressure(&mut self, pressure_hpa: f32) -> Result<(), DHTSensorError> {
+        self.write(SET_AMBIENT_PRESSURE, Some(pressure_hpa as u16))
+            .await?;
+        self.delay.delay_us(COMMAND_US).await;
+        Ok(())
+    }
+
+    // Only available while the periodic measurement is stopped, stops it if necessary.
+    pub async fn serial_number(&mut self) -> Result<u64, DHTSensorError> {
+        self.stop_if_measuring().await?;
+        let words: [u16; 3] = self.read(GET_SERIAL_NUMBER).await?;
+        Ok(words
+            .iter()
+            .fold(0u64, |serial, word| (serial << 16) | *word as u64))
+    }
+
+    async fn stop_if_measuring(&mut self) -> Result<(), DHTSensorError> {
+        if self.measuring {
+            self.stop_periodic_measurement().await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, command: u16, argument: Option<u16>) -> Result<(), DHTSensorError> {
+        let [high, low] = command.to_be_bytes();
+        let result = match argument {
+            Some(argument) => {
+                let [a, b, crc] = word_with_crc(argument);
+                self.i2c.write(self.address, &[high, low, a, b, crc]).await
+            }
+            None => self.i2c.write(self.address, &[high, low]).await,
+        };
+        result.map_err(|_| DHTSensorError::BusError)
+    }
+
+    async fn read<const N: usize>(&mut self, command: u16) -> Result<[u16; N], DHTSensorError> {
+        self.write(command, None).await?;
+        self.delay.delay_us(COMMAND_US).await;
+
+        // At most three words are read at once.
+        let mut buffer = [0u8; 9];
+        let buffer = &mut buffer[..N * 3];
+        self.i2c
+            .read(self.address, buffer)
+            .await
+            .map_err(|_| DHTSensorError::BusError)?;
+        let mut words = [0u16; N];
+        for (value, bytes) in words.iter_mut().zip(buffer.chunks(3)) {
+            *value = word(bytes)?;
+        }
+        Ok(words)
+    }
+}
+
+impl<I: I2c, D: DelayNs> EnvironmentalSensor for Scd4x<I, D> {
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_micros(MEASUREMENT_INTERVAL_US as u64)
+    }
+
+    // Restarts a periodic measurement that failed to start, there is no data before the next
+    // measurement interval.
+    async fn read(&mut self) -> Result<SensorReading, SensorError> {
+        if !self.measuring {
+            self.start_periodic_measurement().await?;
+            return Err(SensorError::NoData);
+        }
+        if !self.data_ready().await? {
+            return Err(SensorError::NoData);
+        }
+        Ok(SensorReading::fresh(self.read_measurement().await?))
+    }
+}
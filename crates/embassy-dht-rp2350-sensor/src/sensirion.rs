@@ -0,0 +1,34 @@
+//! Framing shared by the Sensirion sensors: data is sent as big endian words, each followed
+//! by its CRC-8.
+
+use crate::DHTSensorError;
+
+const CRC_POLYNOMIAL: u8 = 0x31;
+const CRC_INIT: u8 = 0xFF;
+
+/// CRC-8 with polynomial 0x31 and initial value 0xFF, as used by all Sensirion sensors.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(CRC_INIT, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ CRC_POLYNOMIAL
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+// Checks the CRC of a word and its CRC byte.
+pub(crate) fn word(bytes: &[u8]) -> Result<u16, DHTSensorError> {
+    if crc8(&bytes[0..2]) != bytes[2] {
+        return Err(DHTSensorError::ChecksumError);
+    }
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+// A word followed by its CRC byte, as sent with command arguments.
+pub(crate) fn word_with_crc(word: u16) -> [u8; 3] {
+    let [high, low] = word.to_be_bytes();
+    [high, low, crc8(&[high, low])]
+}
@@ -0,0 +1,92 @@
+//! Common interface of the sensor drivers, so the firmware can read any of them the same way.
+
+use crate::{DHTReading, DHTSensorError, DTHResponse};
+use embassy_time::Duration;
+
+// The quantities a sensor measured, the ones it doesn't measure are none.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SensorValues {
+    pub temperature: Option<f32>,
+    pub humidity: Option<f32>,
+    // In hPa.
+    pub pressure: Option<f32>,
+    pub co2_ppm: Option<u16>,
+    // In µg/m³.
+    pub pm1_0: Option<u16>,
+    pub pm2_5: Option<u16>,
+    pub pm10: Option<u16>,
+}
+
+// A reading is cached when it was served from the last successful read, either because the
+// minimum request interval hasn't passed yet or because the sensor read failed with `error`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SensorReading {
+    pub values: SensorValues,
+    pub age: Duration,
+    pub is_cached: bool,
+    pub error: Option<SensorError>,
+}
+
+impl SensorReading {
+    pub fn fresh(values: SensorValues) -> Self {
+        Self {
+            values,
+            age: Duration::MIN,
+            is_cached: false,
+            error: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SensorError {
+    NoData,
+    ChecksumError,
+    InvalidData,
+    Timeout,
+    PinError,
+    BusError,
+}
+
+// Only used from the single threaded embassy executor, there is no need for `Send` futures.
+#[allow(async_fn_in_trait)]
+pub trait EnvironmentalSensor {
+    // Reads more often than this are served from the cache of the driver.
+    fn min_request_interval(&self) -> Duration;
+
+    async fn read(&mut self) -> Result<SensorReading, SensorError>;
+}
+
+impl From<DHTSensorError> for SensorError {
+    fn from(err: DHTSensorError) -> Self {
+        match err {
+            DHTSensorError::NoData => Self::NoData,
+            DHTSensorError::ChecksumError => Self::ChecksumError,
+            DHTSensorError::InvalidData => Self::InvalidData,
+            DHTSensorError::Timeout => Self::Timeout,
+            DHTSensorError::PinError => Self::PinError,
+            DHTSensorError::BusError => Self::BusError,
+        }
+    }
+}
+
+impl From<DTHResponse> for SensorValues {
+    fn from(response: DTHResponse) -> Self {
+        Self {
+            temperature: Some(response.temperature),
+            humidity: Some(response.humidity),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<DHTReading> for SensorReading {
+    fn from(reading: DHTReading) -> Self {
+        Self {
+            values: reading.response.into(),
+            age: reading.age,
+            is_cached: reading.is_cached,
+            error: reading.error.map(SensorError::from),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Async driver for the Sensirion SHT4x (SHT40, SHT41, SHT45) I2C sensors.
+//!
+//! Every command is a single byte. Measurements and the serial number are returned as two
+//! words, each followed by its CRC-8.
+
+use crate::sensirion::word;
+use crate::sensor::{EnvironmentalSensor, SensorError, SensorReading};
+use crate::{DHTSensorError, DTHResponse};
+use embassy_time::Duration;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+// SHT4x-A parts. SHT4x-B parts answer at 0x45 and SHT4x-C parts at 0x46.
+pub const DEFAULT_ADDRESS: u8 = 0x44;
+
+const READ_SERIAL_NUMBER: u8 = 0x89;
+const SOFT_RESET: u8 = 0x94;
+const READ_SERIAL_NUMBER_US: u32 = 1_000;
+const SOFT_RESET_US: u32 = 1_000;
+
+pub use crate::sensirion::crc8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    High,
+    Medium,
+    Low,
+}
+
+impl Precision {
+    const fn command(&self) -> u8 {
+        match self {
+            Self::High => 0xFD,
+            Self::Medium => 0xF6,
+            Self::Low => 0xE0,
+        }
+    }
+
+    // Maximum measurement duration from the datasheet.
+    const fn duration_us(&self) -> u32 {
+        match self {
+            Self::High => 8_300,
+            Self::Medium => 4_500,
+            Self::Low => 1_700,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeaterPower {
+    High,   // 200mW
+    Medium, // 110mW
+    Low,    // 20mW
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeaterDuration {
+    Long,  // 1s
+    Short, // 0.1s
+}
+
+impl HeaterDuration {
+    // Includes the high precision measurement taken when the heater is turned off.
+    const fn duration_us(&self) -> u32 {
+        match self {
+            Self::Long => 1_100_000,
+            Self::Short => 110_000,
+        }
+    }
+}
+
+const fn heater_command(power: HeaterPower, duration: HeaterDuration) -> u8 {
+    match (power, duration) {
+        (HeaterPower::High, HeaterDuration::Long) => 0x39,
+        (HeaterPower::High, HeaterDuration::Short) => 0x32,
+        (HeaterPower::Medium, HeaterDuration::Long) => 0x2F,
+        (HeaterPower::Medium, HeaterDuration::Short) => 0x24,
+        (HeaterPower::Low, HeaterDuration::Long) => 0x1E,
+        (HeaterPower::Low, HeaterDuration::Short) => 0x15,
+    }
+}
+
+pub struct Sht4x<I, D> {
+    i2c: I,
+    delay: D,
+    address: u8,
+    precision: Precision,
+}
+
+impl<I: I2c, D: DelayNs> Sht4x<I, D> {
+    pub fn new(i2c: I, delay: D) -> Self {
+        Self::new_with_address(i2c, delay, DEFAULT_ADDRESS)
+    }
+
+    pub fn new_with_address(i2c: I, delay: D, address: u8) -> Self {
+        Sht4x {
+            i2c,
+            delay,
+            address,
+            precision: Precision::High,
+        }
+    }
+
+    pub fn release(self) -> (I, D) {
+        (self.i2c, self.delay)
+    }
+
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+    }
+
+    pub async fn measure(&mut self) -> Result<DTHResponse, DHTSensorError> {
+        let words = self
+            .command(self.precision.command(), self.precision.duration_us())
+            .await?;
+        Ok(response(words))
+    }
+
+    // Heats the sensor, e.g. to drive off condensation, and returns the measurement taken at
+    // the end of the heating. The datasheet limits the heater to a duty cycle of 10%.
+    pub async fn heat(
+        &mut self,
+        power: HeaterPower,
+        duration: HeaterDuration,
+    ) -> Result<DTHResponse, DHTSensorError> {
+        let words = self
+            .command(heater_command(power, duration), duration.duration_us())
+            .await?;
+        Ok(response(words))
+    }
+
+    pub async fn serial_number(&mut self) -> Result<u32, DHTSensorError> {
+        let [high, low] = self
+            .command(READ_SERIAL_NUMBER, READ_SERIAL_NUMBER_US)
+            .await?;
+        Ok(((high as u32) << 16) | low as u32)
+    }
+
+    pub async fn soft_reset(&mut self) -> Result<(), DHTSensorError> {
+        self.i2c
+            .write(self.address, &[SOFT_RESET])
+            .await
+            .map_err(|_| DHTSensorError::BusError)?;
+        self.delay.delay_us(SOFT_RESET_US).await;
+        Ok(())
+    }
+
+    async fn command(&mut self, command: u8, duration_us: u32) -> Result<[u16; 2], DHTSensorError> {
+        self.i2c
+            .write(self.address, &[command])
+            .await
+            .map_err(|_| DHTSensorError::BusError)?;
+        self.delay.delay_us(duration_us).await;
+
+        let mut buffer = [0u8; 6];
+        self.i2c
+            .read(self.address, &mut buffer)
+            .await
+            .map_err(|_| DHTSensorError::BusError)?;
+        Ok([word(&buffer[0..3])?, word(&buffer[3..6])?])
+    }
+}
+
+impl<I: I2c, D: DelayNs> EnvironmentalSensor for Sht4x<I, D> {
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_micros(self.precision.duration_us() as u64)
+    }
+
+    async fn read(&mut self) -> Result<SensorReading, SensorError> {
+        Ok(SensorReading::fresh(self.measure().await?.into()))
+    }
+}
+
+// The conversion formulas of the datasheet. Humidity is clipped, the sensor can report values
+// slightly outside of 0-100% at the extremes.
+fn response([temperature, humidity]: [u16; 2]) -> DTHResponse {
+    DTHResponse {
+        temperature: -45.0 + 175.0 * temperature as f32 / 65535.0,
+        humidity: (-6.0 + 125.0 * humidity as f32 / 65535.0).clamp(0.0, 100.0),
+    }
+}
@@ -0,0 +1,104 @@
+#![cfg_attr(not(test), no_std)]
+// Metrics derived from the air temperature in °C and the relative humidity in %.
+
+use libm::{expf, logf};
+
+// Magnus coefficients over water, valid from -45 °C to 60 °C.
+const MAGNUS_A: f32 = 17.62;
+const MAGNUS_B: f32 = 243.12;
+
+// Below this dew point in °C the air feels comfortable.
+const COMFORTABLE_DEW_POINT: f32 = 16.0;
+// Above this dew point in °C the air feels oppressive.
+const OPPRESSIVE_DEW_POINT: f32 = 21.0;
+// Below this relative humidity in % the air feels dry.
+const DRY_HUMIDITY: f32 = 30.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Comfort {
+    Dry,
+    Comfortable,
+    Humid,
+    Oppressive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Psychrometrics {
+    // °C
+    pub dew_point: f32,
+    // °C
+    pub heat_index: f32,
+    // g/m³
+    pub absolute_humidity: f32,
+    pub comfort: Comfort,
+}
+
+impl Psychrometrics {
+    pub fn new(temperature: f32, humidity: f32) -> Self {
+        Psychrometrics {
+            dew_point: dew_point(temperature, humidity),
+            heat_index: heat_index(temperature, humidity),
+            absolute_humidity: absolute_humidity(temperature, humidity),
+            comfort: comfort(temperature, humidity),
+        }
+    }
+}
+
+// The Magnus formula, see https://en.wikipedia.org/wiki/Dew_point.
+pub fn dew_point(temperature: f32, humidity: f32) -> f32 {
+    // The logarithm is undefined for completely dry air.
+    let humidity = humidity.clamp(0.1, 100.0);
+    let gamma = logf(humidity / 100.0) + MAGNUS_A * temperature / (MAGNUS_B + temperature);
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+// The NOAA heat index, see https://www.wpc.ncep.noaa.gov/html/heatindex_equation.shtml.
+pub fn heat_index(temperature: f32, humidity: f32) -> f32 {
+    let t = temperature * 9.0 / 5.0 + 32.0;
+    let rh = humidity.clamp(0.0, 100.0);
+
+    // Steadman's simple formula is accurate enough for mild temperatures.
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let heat_index = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let rothfusz = -42.379 + 2.049_015_2 * t + 10.143_332 * rh
+            - 0.224_755_4 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            rothfusz - (13.0 - rh) / 4.0 * libm::sqrtf((17.0 - (t - 95.0).abs()) / 17.0)
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            rothfusz + (rh - 85.0) / 10.0 * (87.0 - t) / 5.0
+        } else {
+            rothfusz
+        }
+    };
+    (heat_index - 32.0) * 5.0 / 9.0
+}
+
+// Grams of water vapour per cubic meter of air, from the saturation vapour pressure.
+pub fn absolute_humidity(temperature: f32, humidity: f32) -> f32 {
+    let saturation_vapour_pressure =
+        6.112 * expf(MAGNUS_A * temperature / (MAGNUS_B + temperature));
+    saturation_vapour_pressure * humidity.clamp(0.0, 100.0) * 2.1674 / (273.15 + temperature)
+}
+
+pub fn comfort(temperature: f32, humidity: f32) -> Comfort {
+    let dew_point = dew_point(temperature, humidity);
+    if humidity < DRY_HUMIDITY {
+        Comfort::Dry
+    } else if dew_point < COMFORTABLE_DEW_POINT {
+        Comfort::Comfortable
+    } else if dew_point < OPPRESSIVE_DEW_POINT {
+        Comfort::Humid
+    } else {
+        Comfort::Oppressive
+    }
+}
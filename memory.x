@@ -4,7 +4,16 @@ MEMORY {
      *
      * 2 MiB is a safe default here, although a Pico 2 has 4 MiB.
      */
-FLASH : ORIGIN = 0x10000000, LENGTH = 2048K
+FLASH : ORIGIN = 0x10000000, LENGTH = 1980K
+          /*
+           * Measurements that couldn't be sent yet, see `queue`.
+           */
+          QUEUE : ORIGIN = 0x101EF000, LENGTH = 64K
+          /*
+           * The last sector of the flash is reserved for the persistent config,
+           * see `config::storage`.
+           */
+          CONFIG : ORIGIN = 0x101FF000, LENGTH = 4K
           /*
            * RAM consists of 8 banks, SRAM0-SRAM7, with a striped mapping.
            * This is usually good for performance, as it distributes load on
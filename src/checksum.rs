@@ -0,0 +1,7 @@
+pub(crate) fn fletcher16(data: &[u8]) -> u16 {
+    let (sum1, sum2) = data.iter().fold((0u16, 0u16), |(sum1, sum2), byte| {
+        let sum1 = (sum1 + *byte as u16) % 255;
+        (sum1, (sum2 + sum1) % 255)
+    });
+    (sum2 << 8) | sum1
+}
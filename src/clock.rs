@@ -0,0 +1,48 @@
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_time::Instant;
+
+// Wall-clock time in milliseconds since the Unix epoch, kept as an offset over the uptime.
+// It is unknown until the first synchronisation.
+pub struct WallClock {
+    // Unix time at an uptime of zero
+    epoch_millis: Mutex<NoopRawMutex, Cell<Option<u64>>>,
+}
+
+impl WallClock {
+    pub const fn new() -> Self {
+        WallClock {
+            epoch_millis: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    // Sets the clock to `unix_millis` at `at`.
+    pub fn set(&self, unix_millis: u64, at: Instant) {
+        self.epoch_millis
+            .lock(|epoch_millis| epoch_millis.set(unix_millis.checked_sub(at.as_millis())));
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.epoch_millis
+            .lock(|epoch_millis| epoch_millis.get())
+            .is_some()
+    }
+
+    pub fn at(&self, instant: Instant) -> Option<u64> {
+        self.epoch_millis
+            .lock(|epoch_millis| epoch_millis.get())
+            .map(|epoch_millis| epoch_millis + instant.as_millis())
+    }
+
+    pub fn now(&self) -> Option<u64> {
+        self.at(Instant::now())
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,271 @@
+use alloc::vec::Vec;
+use serde::Deserialize;
+use serde_json_core::heapless;
+
+use crate::Measurement;
+use crate::checksum::fletcher16;
+use crate::config::error::ConfigError;
+use crate::device;
+
+// Sensor indices 0 to 8, see `temperature_and_humidity::tasks::Sensors`.
+pub const SENSOR_COUNT: usize = 9;
+// DS18B20 probes share the index of their bus and are calibrated one by one.
+pub const MAX_PROBE_CALIBRATIONS: usize = 16;
+
+const MAGIC: [u8; 4] = *b"SHCC";
+const CALIBRATION_BYTES: usize = 16;
+const PROBE_CALIBRATION_BYTES: usize = 1 + 8 + CALIBRATION_BYTES;
+pub const CALIBRATION_CONFIG_LEN: usize = MAGIC.len()
+    + 2
+    + SENSOR_COUNT * CALIBRATION_BYTES
+    + 1
+    + MAX_PROBE_CALIBRATIONS * PROBE_CALIBRATION_BYTES
+    + 2;
+
+// A linear correction of the raw values: `raw * gain + offset`.
+// Values left out of a provisioned calibration keep the identity.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Calibration {
+    pub temperature_offset: f32,
+    pub temperature_gain: f32,
+    pub humidity_offset: f32,
+    pub humidity_gain: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            temperature_offset: 0.0,
+            temperature_gain: 1.0,
+            humidity_offset: 0.0,
+            humidity_gain: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    pub fn temperature(&self, temperature: f32) -> f32 {
+        temperature * self.temperature_gain + self.temperature_offset
+    }
+
+    pub fn humidity(&self, humidity: f32) -> f32 {
+        (humidity * self.humidity_gain + self.humidity_offset).clamp(0.0, 100.0)
+    }
+
+    fn to_bytes(self) -> [u8; CALIBRATION_BYTES] {
+        let mut bytes = [0; CALIBRATION_BYTES];
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip([
+            self.temperature_offset,
+            self.temperature_gain,
+            self.humidity_offset,
+            self.humidity_gain,
+        ]) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn is_valid(&self) -> bool {
+        [
+            self.temperature_offset,
+            self.temperature_gain,
+            self.humidity_offset,
+            self.humidity_gain,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ConfigError> {
+        let mut values = [0.0; 4];
+        for (value, chunk) in values.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            if !value.is_finite() {
+                return Err(ConfigError::InvalidData);
+            }
+        }
+        let [
+            temperature_offset,
+            temperature_gain,
+            humidity_offset,
+            humidity_gain,
+        ] = values;
+        Ok(Calibration {
+            temperature_offset,
+            temperature_gain,
+            humidity_offset,
+            humidity_gain,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProbeCalibration {
+    pub sensor: u8,
+    pub probe: u64,
+    pub calibration: Calibration,
+}
+
+// Version 0 is the identity calibration of a device that was never calibrated,
+// its measurements are reported as raw.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CalibrationConfig {
+    pub version: u16,
+    pub sensors: [Calibration; SENSOR_COUNT],
+    pub probes: Vec<ProbeCalibration>,
+}
+
+impl CalibrationConfig {
+    // Probes without a calibration of their own are reported with the identity, the
+    // calibration of their bus belongs to another probe.
+    pub fn calibration(&self, sensor: u8, probe: Option<u64>) -> Calibration {
+        match probe {
+            Some(probe) => self
+                .probes
+                .iter()
+                .find(|entry| entry.sensor == sensor && entry.probe == probe)
+                .map(|entry| entry.calibration)
+                .unwrap_or_default(),
+            None => self
+                .sensors
+                .get(sensor as usize)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn apply(&self, measurement: &mut Measurement) {
+        if self.version == 0
+            || (measurement.temperature.is_none() && measurement.humidity.is_none())
+        {
+            return;
+        }
+        let calibration = self.calibration(measurement.sensor, measurement.probe);
+        measurement.temperature = measurement
+            .temperature
+            .map(|temperature| calibration.temperature(temperature));
+        measurement.humidity = measurement
+            .humidity
+            .map(|humidity| calibration.humidity(humidity));
+        measurement.calibration = Some(self.version);
+    }
+
+    pub fn to_bytes(&self) -> [u8; CALIBRATION_CONFIG_LEN] {
+        let mut bytes = [0; CALIBRATION_CONFIG_LEN];
+        let (header, rest) = bytes.split_at_mut(MAGIC.len() + 2);
+        header[..MAGIC.len()].copy_from_slice(&MAGIC);
+        header[MAGIC.len()..].copy_from_slice(&self.version.to_le_bytes());
+        let (sensors, rest) = rest.split_at_mut(SENSOR_COUNT * CALIBRATION_BYTES);
+        for (chunk, calibration) in sensors
+            .chunks_exact_mut(CALIBRATION_BYTES)
+            .zip(self.sensors)
+        {
+            chunk.copy_from_slice(&calibration.to_bytes());
+        }
+        // More probes are rejected when provisioned.
+        let probes = &self.probes[..self.probes.len().min(MAX_PROBE_CALIBRATIONS)];
+        rest[0] = probes.len() as u8;
+        for (chunk, entry) in rest[1..]
+            .chunks_exact_mut(PROBE_CALIBRATION_BYTES)
+            .zip(probes)
+        {
+            chunk[0] = entry.sensor;
+            chunk[1..9].copy_from_slice(&entry.probe.to_le_bytes());
+            chunk[9..].copy_from_slice(&entry.calibration.to_bytes());
+        }
+        let checksum = fletcher16(&bytes[..CALIBRATION_CONFIG_LEN - 2]);
+        bytes[CALIBRATION_CONFIG_LEN - 2..].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; CALIBRATION_CONFIG_LEN]) -> Result<Self, ConfigError> {
+        if bytes.iter().all(|byte| *byte == 0xFF) {
+            return Err(ConfigError::Erased);
+        }
+        if bytes[..MAGIC.len()] != MAGIC {
+            return Err(ConfigError::InvalidData);
+        }
+        let (data, checksum) = bytes.split_at(CALIBRATION_CONFIG_LEN - 2);
+        if fletcher16(data).to_le_bytes() != checksum {
+            return Err(ConfigError::ChecksumError);
+        }
+        let version = u16::from_le_bytes([data[MAGIC.len()], data[MAGIC.len() + 1]]);
+        let (sensor_data, probe_data) =
+            data[MAGIC.len() + 2..].split_at(SENSOR_COUNT * CALIBRATION_BYTES);
+        let mut sensors = [Calibration::default(); SENSOR_COUNT];
+        for (calibration, chunk) in sensors
+            .iter_mut()
+            .zip(sensor_data.chunks_exact(CALIBRATION_BYTES))
+        {
+            *calibration = Calibration::from_bytes(chunk)?;
+        }
+        let count = probe_data[0] as usize;
+        if count > MAX_PROBE_CALIBRATIONS {
+            return Err(ConfigError::InvalidData);
+        }
+        let mut probes = Vec::with_capacity(count);
+        for chunk in probe_data[1..]
+            .chunks_exact(PROBE_CALIBRATION_BYTES)
+            .take(count)
+        {
+            let mut probe = [0; 8];
+            probe.copy_from_slice(&chunk[1..9]);
+            probes.push(ProbeCalibration {
+                sensor: chunk[0],
+                probe: u64::from_le_bytes(probe),
+                calibration: Calibration::from_bytes(&chunk[9..])?,
+            });
+        }
+        Ok(CalibrationConfig {
+            version,
+            sensors,
+            probes,
+        })
+    }
+}
+
+// A calibration as served by `/api/config`, a sensor or a single DS18B20 probe.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CalibrationEntry {
+    pub sensor: u8,
+    #[serde(default, deserialize_with = "device::deserialize_probe")]
+    pub probe: Option<u64>,
+    #[serde(default)]
+    pub calibration: Calibration,
+}
+
+// Sensors left out keep the identity calibration.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ProvisionedCalibration {
+    pub version: u16,
+    pub sensors: heapless::Vec<CalibrationEntry, { SENSOR_COUNT + MAX_PROBE_CALIBRATIONS }>,
+}
+
+impl TryFrom<&ProvisionedCalibration> for CalibrationConfig {
+    type Error = ConfigError;
+
+    fn try_from(provisioned: &ProvisionedCalibration) -> Result<Self, ConfigError> {
+        let mut config = CalibrationConfig {
+            version: provisioned.version,
+            ..Default::default()
+        };
+        for entry in &provisioned.sensors {
+            if entry.sensor as usize >= SENSOR_COUNT || !entry.calibration.is_valid() {
+                return Err(ConfigError::InvalidData);
+            }
+            match entry.probe {
+                Some(_) if config.probes.len() == MAX_PROBE_CALIBRATIONS => {
+                    return Err(ConfigError::InvalidData);
+                }
+                Some(probe) => config.probes.push(ProbeCalibration {
+                    sensor: entry.sensor,
+                    probe,
+                    calibration: entry.calibration,
+                }),
+                None => config.sensors[entry.sensor as usize] = entry.calibration,
+            }
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,19 @@
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    // The config sector was never written.
+    Erased,
+    InvalidData,
+    ChecksumError,
+    FlashError,
+}
+
+impl defmt::Format for ConfigError {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match self {
+            Self::Erased => defmt::write!(fmt, "{}", "Erased"),
+            Self::InvalidData => defmt::write!(fmt, "{}", "InvalidData"),
+            Self::ChecksumError => defmt::write!(fmt, "{}", "ChecksumError"),
+            Self::FlashError => defmt::write!(fmt, "{}", "FlashError"),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use embassy_time::Duration;
+use serde::Deserialize;
+
+use crate::config::calibration::ProvisionedCalibration;
+use crate::config::error::ConfigError;
+
+pub const DEFAULT_SAMPLING_INTERVAL: Duration = Duration::from_secs(10);
+pub const MIN_SAMPLING_INTERVAL_SECS: u64 = 1;
+pub const MAX_SAMPLING_INTERVAL_SECS: u64 = 60 * 60;
+
+// The runtime config served by `/api/config`, unset values keep their current setting.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct DeviceConfig {
+    pub sampling_interval_secs: Option<u64>,
+    // Stored in flash when its version differs from the one in use.
+    pub calibration: Option<ProvisionedCalibration>,
+}
+
+impl DeviceConfig {
+    pub fn from_json(body: &[u8]) -> Result<Self, ConfigError> {
+        serde_json_core::from_slice(body)
+            .map(|(config, _)| config)
+            .map_err(|_| ConfigError::InvalidData)
+    }
+
+    // Out of range values are clamped, a zero interval would never yield and a huge one
+    // overflows the uptime.
+    pub fn sampling_interval(&self) -> Option<Duration> {
+        self.sampling_interval_secs.map(|secs| {
+            Duration::from_secs(secs.clamp(MIN_SAMPLING_INTERVAL_SECS, MAX_SAMPLING_INTERVAL_SECS))
+        })
+    }
+}
+
+// Sensors can't be read more often than their driver allows.
+pub fn clamp_interval(interval: Duration, min_request_interval: Duration) -> Duration {
+    interval
+        .min(Duration::from_secs(MAX_SAMPLING_INTERVAL_SECS))
+        .max(Duration::from_secs(MIN_SAMPLING_INTERVAL_SECS))
+        .max(min_request_interval)
+}
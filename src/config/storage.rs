@@ -0,0 +1,62 @@
+use core::cell::RefCell;
+use defmt::{info, warn};
+use embassy_embedded_hal::flash::partition::BlockingPartition;
+use embassy_rp::flash::{Blocking, ERASE_SIZE, Flash};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+use crate::config::calibration::{CALIBRATION_CONFIG_LEN, CalibrationConfig};
+use crate::config::error::ConfigError;
+
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+// The last sector of the flash, reserved as CONFIG in memory.x.
+const CONFIG_OFFSET: u32 = (FLASH_SIZE - ERASE_SIZE) as u32;
+const CONFIG_SIZE: u32 = ERASE_SIZE as u32;
+// The sectors in front of the config, reserved as QUEUE in memory.x.
+pub const QUEUE_SIZE: u32 = 16 * ERASE_SIZE as u32;
+const QUEUE_OFFSET: u32 = CONFIG_OFFSET - QUEUE_SIZE;
+
+pub type BoardFlash = Flash<'static, FLASH, Blocking, FLASH_SIZE>;
+// The queue and the config are written by different tasks, each through its own partition.
+pub type SharedFlash = Mutex<NoopRawMutex, RefCell<BoardFlash>>;
+pub type FlashPartition = BlockingPartition<'static, NoopRawMutex, BoardFlash>;
+
+pub fn queue_partition(flash: &'static SharedFlash) -> FlashPartition {
+    FlashPartition::new(flash, QUEUE_OFFSET, QUEUE_SIZE)
+}
+
+pub fn config_partition(flash: &'static SharedFlash) -> FlashPartition {
+    FlashPartition::new(flash, CONFIG_OFFSET, CONFIG_SIZE)
+}
+
+// Falls back to the identity calibration if none was stored yet.
+pub fn load_calibration(config_flash: &mut FlashPartition) -> CalibrationConfig {
+    let mut bytes = [0; CALIBRATION_CONFIG_LEN];
+    let calibration = config_flash
+        .read(0, &mut bytes)
+        .map_err(|_| ConfigError::FlashError)
+        .and_then(|_| CalibrationConfig::from_bytes(&bytes));
+    match calibration {
+        Ok(calibration) => {
+            info!("Loaded calibration version {}", calibration.version);
+            calibration
+        }
+        Err(ConfigError::Erased) => CalibrationConfig::default(),
+        Err(err) => {
+            warn!("Ignoring stored calibration: {}", err);
+            CalibrationConfig::default()
+        }
+    }
+}
+
+pub fn store_calibration(
+    config_flash: &mut FlashPartition,
+    calibration: &CalibrationConfig,
+) -> Result<(), ConfigError> {
+    config_flash
+        .erase(0, CONFIG_SIZE)
+        .and_then(|_| config_flash.write(0, &calibration.to_bytes()))
+        .map_err(|_| ConfigError::FlashError)
+}
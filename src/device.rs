@@ -0,0 +1,77 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+// Tells the hubs posting to the same server apart, derived from the unique ID of the chip.
+// It is sent as 16 lowercase hex digits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceId(u64);
+
+impl DeviceId {
+    pub fn new(chip_id: u64) -> Self {
+        DeviceId(chip_id)
+    }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    pub fn to_hex(&self) -> [u8; 16] {
+        to_hex(self.0)
+    }
+}
+
+fn to_hex(value: u64) -> [u8; 16] {
+    let mut hex = [0; 16];
+    for (i, digit) in hex.iter_mut().enumerate() {
+        *digit = HEX_DIGITS[((value >> (60 - 4 * i)) & 0xF) as usize];
+    }
+    hex
+}
+
+fn serialize_hex<S: Serializer>(value: u64, serializer: S) -> Result<S::Ok, S::Error> {
+    let hex = to_hex(value);
+    // Hex digits are always valid UTF-8.
+    serializer.serialize_str(core::str::from_utf8(&hex).unwrap_or_default())
+}
+
+impl Serialize for DeviceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_hex(self.0, serializer)
+    }
+}
+
+// DS18B20 ROMs are sent as 16 lowercase hex digits like the device id, JSON numbers lose the
+// precision of a u64 in JavaScript.
+pub fn serialize_probe<S: Serializer>(
+    probe: &Option<u64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match probe {
+        Some(probe) => serialize_hex(*probe, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize_probe<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    let hex = <&str>::deserialize(deserializer)?;
+    if hex.len() != HEX_DIGITS.len() || !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(serde::de::Error::custom("probe must be 16 hex digits"));
+    }
+    u64::from_str_radix(hex, 16)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom("probe must be 16 hex digits"))
+}
+
+impl defmt::Format for DeviceId {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        let hex = self.to_hex();
+        defmt::write!(
+            fmt,
+            "{=str}",
+            core::str::from_utf8(&hex).unwrap_or_default()
+        )
+    }
+}
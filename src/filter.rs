@@ -0,0 +1,187 @@
+use embassy_time::Instant;
+
+pub const MAX_MEDIAN_WINDOW: usize = 9;
+// A sensor that keeps jumping is believed after this many rejected samples in a row.
+const MAX_CONSECUTIVE_REJECTIONS: u8 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilterConfig {
+    // Number of samples the median is taken of, 1 disables the median.
+    pub median_window: usize,
+    // Weight of a new sample in the exponential moving average, 1.0 disables the average.
+    pub ema_alpha: f32,
+    // Largest accepted change per second compared to the last accepted sample.
+    pub max_rate_per_sec: Option<f32>,
+}
+
+impl FilterConfig {
+    pub const fn temperature() -> Self {
+        FilterConfig {
+            median_window: 3,
+            ema_alpha: 0.5,
+            max_rate_per_sec: Some(0.5),
+        }
+    }
+
+    pub const fn humidity() -> Self {
+        FilterConfig {
+            median_window: 3,
+            ema_alpha: 0.5,
+            max_rate_per_sec: Some(2.0),
+        }
+    }
+}
+
+// Rejects rate of change outliers, then smooths the remaining samples with
+// a median window followed by an exponential moving average.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    config: FilterConfig,
+    window: [f32; MAX_MEDIAN_WINDOW],
+    window_len: usize,
+    next: usize,
+    average: Option<f32>,
+    last_sample: Option<(f32, Instant)>,
+    consecutive_rejections: u8,
+    rejected: u32,
+}
+
+impl Filter {
+    pub fn new(config: FilterConfig) -> Self {
+        Filter {
+            config: FilterConfig {
+                median_window: config.median_window.clamp(1, MAX_MEDIAN_WINDOW),
+                ema_alpha: config.ema_alpha.clamp(f32::EPSILON, 1.0),
+                ..config
+            },
+            window: [0.0; MAX_MEDIAN_WINDOW],
+            window_len: 0,
+            next: 0,
+            average: None,
+            last_sample: None,
+            consecutive_rejections: 0,
+            rejected: 0,
+        }
+    }
+
+    // Number of samples rejected as outliers since the filter was created.
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+
+    // Returns the filtered value, or `None` if the sample was rejected.
+    pub fn push(&mut self, sample: f32, at: Instant) -> Option<f32> {
+        if self.rejects(sample, at) {
+            return None;
+        }
+        Some(self.accept(sample, at))
+    }
+
+    // Counts an outlier, it is believed after too many in a row. The sample has to be
+    // accepted afterwards unless it was rejected.
+    fn rejects(&mut self, sample: f32, at: Instant) -> bool {
+        if !self.is_outlier(sample, at) {
+            return false;
+        }
+        self.rejected += 1;
+        self.consecutive_rejections += 1;
+        if self.consecutive_rejections < MAX_CONSECUTIVE_REJECTIONS {
+            return true;
+        }
+        // The sensor moved on, the old samples no longer describe it.
+        self.reset();
+        false
+    }
+
+    fn accept(&mut self, sample: f32, at: Instant) -> f32 {
+        self.consecutive_rejections = 0;
+        self.last_sample = Some((sample, at));
+
+        self.window[self.next] = sample;
+        self.next = (self.next + 1) % self.config.median_window;
+        self.window_len = (self.window_len + 1).min(self.config.median_window);
+        let median = self.median();
+
+        let average = match self.average {
+            Some(average) => average + self.config.ema_alpha * (median - average),
+            None => median,
+        };
+        self.average = Some(average);
+        average
+    }
+
+    fn is_outlier(&self, sample: f32, at: Instant) -> bool {
+        match (self.config.max_rate_per_sec, self.last_sample) {
+            (Some(max_rate_per_sec), Some((last_sample, last_at))) => {
+                // Samples taken in the same millisecond are compared as one millisecond apart.
+                let elapsed_secs =
+                    at.saturating_duration_since(last_at).as_millis().max(1) as f32 / 1000.0;
+                (sample - last_sample).abs() / elapsed_secs > max_rate_per_sec
+            }
+            _ => false,
+        }
+    }
+
+    fn median(&self) -> f32 {
+        let mut sorted = [0.0; MAX_MEDIAN_WINDOW];
+        let sorted = &mut sorted[..self.window_len];
+        sorted.copy_from_slice(&self.window[..self.window_len]);
+        sorted.sort_unstable_by(f32::total_cmp);
+        let middle = sorted.len() / 2;
+        if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        }
+    }
+
+    fn reset(&mut self) {
+        self.window_len = 0;
+        self.next = 0;
+        self.average = None;
+    }
+}
+
+// Filters temperature and humidity of a single sensor, a sample is rejected
+// as a whole if either of its values is an outlier.
+#[derive(Clone, Debug)]
+pub struct MeasurementFilter {
+    temperature: Filter,
+    humidity: Filter,
+    rejected: u32,
+}
+
+impl Default for MeasurementFilter {
+    fn default() -> Self {
+        Self::new(FilterConfig::temperature(), FilterConfig::humidity())
+    }
+}
+
+impl MeasurementFilter {
+    pub fn new(temperature: FilterConfig, humidity: FilterConfig) -> Self {
+        MeasurementFilter {
+            temperature: Filter::new(temperature),
+            humidity: Filter::new(humidity),
+            rejected: 0,
+        }
+    }
+
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+
+    // Returns the filtered temperature and humidity. Both values are checked before either
+    // filter takes its sample, a rejected measurement leaves the windows of both unchanged.
+    pub fn push(&mut self, temperature: f32, humidity: f32, at: Instant) -> Option<(f32, f32)> {
+        let temperature_rejected = self.temperature.rejects(temperature, at);
+        let humidity_rejected = self.humidity.rejects(humidity, at);
+        if temperature_rejected || humidity_rejected {
+            self.rejected += 1;
+            return None;
+        }
+        Some((
+            self.temperature.accept(temperature, at),
+            self.humidity.accept(humidity, at),
+        ))
+    }
+}
@@ -3,22 +3,103 @@ extern crate alloc;
 
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
 use serde::Serialize;
 
-#[derive(Clone, Serialize)]
+use crate::config::sampling::DeviceConfig;
+use crate::device::DeviceId;
+
+#[derive(Clone, Default, Serialize)]
 pub struct Measurement {
-    pub humidity: f32,
-    pub temperature: f32,
+    pub sensor: u8,
+    // ROM of the DS18B20 probe, several probes share the sensor index of their bus.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "device::serialize_probe"
+    )]
+    pub probe: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub co2_ppm: Option<u16>,
+    // Particulate matter in µg/m³
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pm1_0: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pm2_5: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pm10: Option<u16>,
+    // Samples of the sensor rejected as outliers since the boot, only sent with filtered
+    // measurements and not queued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<u32>,
+    // Version of the calibration applied to temperature and humidity, not sent for raw values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<u16>,
+    // Unix time in milliseconds, unknown until the clock has been synchronised.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<DeviceId>,
+}
+
+// Only the values the sensor measured.
+impl defmt::Format for Measurement {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        defmt::write!(fmt, "Sensor {}:", self.sensor);
+        if let Some(probe) = self.probe {
+            defmt::write!(fmt, " Probe: {:x}", probe);
+        }
+        if let Some(temperature) = self.temperature {
+            defmt::write!(fmt, " Temperature: {}", temperature);
+        }
+        if let Some(humidity) = self.humidity {
+            defmt::write!(fmt, " Humidity: {}", humidity);
+        }
+        if let Some(pressure) = self.pressure {
+            defmt::write!(fmt, " Pressure: {}", pressure);
+        }
+        if let Some(co2_ppm) = self.co2_ppm {
+            defmt::write!(fmt, " CO2: {}", co2_ppm);
+        }
+        if let (Some(pm1_0), Some(pm2_5), Some(pm10)) = (self.pm1_0, self.pm2_5, self.pm10) {
+            defmt::write!(fmt, " PM1.0: {}, PM2.5: {}, PM10: {}", pm1_0, pm2_5, pm10);
+        }
+    }
 }
 pub type TempHumidityChannel = Channel<NoopRawMutex, Measurement, 4>;
 
 pub type LedChannel = Channel<NoopRawMutex, bool, 4>;
 
+pub type DeviceConfigSignal = Signal<NoopRawMutex, DeviceConfig>;
+
+pub mod config {
+    pub mod calibration;
+    pub mod error;
+    pub mod sampling;
+    #[cfg(feature = "board")]
+    pub mod storage;
+}
+
+mod checksum;
+pub mod clock;
+pub mod device;
+pub mod filter;
+pub mod publish;
+pub mod queue;
+
 pub mod network {
     pub mod api;
+    pub mod batch;
     #[cfg(feature = "board")]
     pub mod controller;
     pub mod error;
+    pub mod link;
+    pub mod sntp;
 }
 
 pub mod game {
@@ -37,5 +118,13 @@ pub mod game {
 pub mod temperature_and_humidity {
     mod error;
     pub mod tasks;
-    pub use embassy_rp::peripherals::PIO0;
+    pub use embassy_dht_rp2350_sensor::bme280::Bme280;
+    pub use embassy_dht_rp2350_sensor::ds18b20::Ds18b20;
+    pub use embassy_dht_rp2350_sensor::pms5003::Pms5003;
+    pub use embassy_dht_rp2350_sensor::scd4x::Scd4x;
+    pub use embassy_dht_rp2350_sensor::sht4x::Sht4x;
+    pub use embassy_dht_rp2350_sensor::{
+        DHTPioProgram, DHTSensor, OneWirePio, OneWirePioProgram, SensorModel,
+    };
+    pub use embassy_rp::peripherals::{PIO0, PIO2};
 }
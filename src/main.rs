@@ -1,8 +1,11 @@
 #![no_std]
 #![no_main]
 
+use core::cell::RefCell;
 use cyw43_pio::{PioSpi, RM2_CLOCK_DIVIDER};
+use defmt::{info, warn};
 use embassy_executor::Spawner;
+use embassy_rp::flash::Flash;
 use embassy_rp::{
     bind_interrupts, dma,
     gpio::{Input, Level, Output, Pull},
@@ -10,24 +13,64 @@ use embassy_rp::{
     peripherals::{DMA_CH0, I2C1, PIO1},
     pio::{InterruptHandler, Pio},
 };
+use embassy_sync::blocking_mutex;
 use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
 use embedded_alloc::LlffHeap;
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
+#[cfg(feature = "i2c-sensors")]
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+#[cfg(feature = "dht-pulse-width")]
+use embassy_rp::peripherals::DMA_CH1;
+#[cfg(feature = "i2c-sensors")]
+use embassy_rp::peripherals::I2C0;
+#[cfg(feature = "pms5003")]
+use embassy_rp::peripherals::UART0;
+#[cfg(feature = "pms5003")]
+use embassy_rp::uart::{self, BufferedUart, Config as UartConfig};
+#[cfg(feature = "i2c-sensors")]
+use embassy_sync::mutex::Mutex;
+#[cfg(any(feature = "i2c-sensors", feature = "ds18b20"))]
+use embassy_time::Delay;
+use rp2350_sensor_hub::DeviceConfigSignal;
 use rp2350_sensor_hub::LedChannel;
 use rp2350_sensor_hub::TempHumidityChannel;
+use rp2350_sensor_hub::clock::WallClock;
+use rp2350_sensor_hub::config::storage;
+use rp2350_sensor_hub::device::DeviceId;
 use rp2350_sensor_hub::game;
 use rp2350_sensor_hub::network;
 #[cfg(feature = "temperature")]
 use rp2350_sensor_hub::temperature_and_humidity;
+#[cfg(feature = "pms5003")]
+use rp2350_sensor_hub::temperature_and_humidity::Pms5003;
+#[cfg(feature = "i2c-sensors")]
+use rp2350_sensor_hub::temperature_and_humidity::{Bme280, Scd4x, Sht4x, tasks::I2cBus};
 #[cfg(feature = "temperature")]
-use rp2350_sensor_hub::temperature_and_humidity::PIO0;
+use rp2350_sensor_hub::temperature_and_humidity::{
+    DHTPioProgram, DHTSensor, PIO0, SensorModel, tasks::Sensors,
+};
+#[cfg(feature = "ds18b20")]
+use rp2350_sensor_hub::temperature_and_humidity::{Ds18b20, OneWirePio, OneWirePioProgram, PIO2};
 
 static LED_CHANNEL: StaticCell<LedChannel> = StaticCell::new();
 static TEMP_HUMIDITY_CHANNEL: StaticCell<TempHumidityChannel> = StaticCell::new();
+static DEVICE_CONFIG_SIGNAL: StaticCell<DeviceConfigSignal> = StaticCell::new();
+static FLASH: StaticCell<storage::SharedFlash> = StaticCell::new();
+static WALL_CLOCK: StaticCell<WallClock> = StaticCell::new();
+#[cfg(feature = "i2c-sensors")]
+static I2C_BUS: StaticCell<I2cBus> = StaticCell::new();
+// Room for two frames of the sensor, commands are 7 bytes.
+#[cfg(feature = "pms5003")]
+static PMS5003_RX_BUFFER: StaticCell<[u8; 64]> = StaticCell::new();
+#[cfg(feature = "pms5003")]
+static PMS5003_TX_BUFFER: StaticCell<[u8; 16]> = StaticCell::new();
 
 const I2C_FREQUENCY: u32 = 400_000;
+#[cfg(feature = "pms5003")]
+const PMS5003_BAUD_RATE: u32 = 9_600;
 
 #[global_allocator]
 static HEAP: LlffHeap = LlffHeap::empty();
@@ -37,7 +80,16 @@ bind_interrupts!(struct Irqs {
     PIO1_IRQ_0 => InterruptHandler<PIO1>;
     #[cfg(feature = "temperature")]
     PIO0_IRQ_0 => InterruptHandler<PIO0>;
+    #[cfg(not(feature = "dht-pulse-width"))]
     DMA_IRQ_0 => dma::InterruptHandler<DMA_CH0>;
+    #[cfg(feature = "dht-pulse-width")]
+    DMA_IRQ_0 => dma::InterruptHandler<DMA_CH0>, dma::InterruptHandler<DMA_CH1>;
+    #[cfg(feature = "i2c-sensors")]
+    I2C0_IRQ => i2c::InterruptHandler<I2C0>;
+    #[cfg(feature = "ds18b20")]
+    PIO2_IRQ_0 => InterruptHandler<PIO2>;
+    #[cfg(feature = "pms5003")]
+    UART0_IRQ => uart::BufferedInterruptHandler<UART0>;
 });
 
 #[embassy_executor::main]
@@ -57,6 +109,22 @@ async fn main(spawner: Spawner) {
     game::tasks::spawn_tasks(&spawner, sensor, led_channel, i2c).await;
 
     let temp_humidity_channel = TEMP_HUMIDITY_CHANNEL.init(Channel::new());
+    let flash = FLASH.init(blocking_mutex::Mutex::new(RefCell::new(
+        Flash::new_blocking(p.FLASH),
+    )));
+    let device_config_signal = DEVICE_CONFIG_SIGNAL.init(Signal::new());
+    let wall_clock = WALL_CLOCK.init(WallClock::new());
+    let device = match embassy_rp::otp::get_chipid() {
+        Ok(chip_id) => {
+            let device = DeviceId::new(chip_id);
+            info!("Device id: {}", device);
+            Some(device)
+        }
+        Err(err) => {
+            warn!("Reading the chip id failed with: {}", err);
+            None
+        }
+    };
     #[cfg(feature = "temperature")]
     {
         let pio = p.PIO0;
@@ -66,12 +134,67 @@ async fn main(spawner: Spawner) {
         let mut pin = common.make_pio_pin(p.PIN_17);
         pin.set_pull(Pull::Up);
 
-        temperature_and_humidity::tasks::spawn_tasks(
-            &spawner,
+        #[cfg(not(feature = "dht-pulse-width"))]
+        let dht_sensor = DHTSensor::new(
+            &DHTPioProgram::load(&mut common),
+            pin,
+            sm0,
+            SensorModel::default(),
+        );
+        #[cfg(feature = "dht-pulse-width")]
+        let dht_sensor = DHTSensor::new_pulse_width(
+            &DHTPioProgram::load_pulse_width(&mut common),
             pin,
-            common,
             sm0,
+            dma::Channel::new(p.DMA_CH1, Irqs),
+            SensorModel::default(),
+        );
+        let mut sensors = Sensors::default();
+        // A DHT sensor is sent with the number of its state machine as sensor index.
+        sensors.add(0, dht_sensor);
+        #[cfg(feature = "i2c-sensors")]
+        {
+            let i2c_bus = I2C_BUS.init(Mutex::new(I2c::new_async(
+                p.I2C0, p.PIN_5, p.PIN_4, Irqs, config,
+            )));
+            sensors.add(4, Sht4x::new(I2cDevice::new(i2c_bus), Delay));
+            sensors.add(5, Bme280::new(I2cDevice::new(i2c_bus), Delay));
+            sensors.add(7, Scd4x::start(I2cDevice::new(i2c_bus), Delay).await);
+        }
+        #[cfg(feature = "ds18b20")]
+        {
+            let Pio {
+                mut common, sm0, ..
+            } = Pio::new(p.PIO2, Irqs);
+            // The bus has an external 4.7k pull-up.
+            let pin = common.make_pio_pin(p.PIN_22);
+            let bus = OneWirePio::new(&OneWirePioProgram::load(&mut common), pin, sm0);
+            // All probes on the bus share one sensor index.
+            sensors.add(6, Ds18b20::new(bus, Delay));
+        }
+        #[cfg(feature = "pms5003")]
+        {
+            let mut uart_config = UartConfig::default();
+            uart_config.baudrate = PMS5003_BAUD_RATE;
+            let uart = BufferedUart::new(
+                p.UART0,
+                p.PIN_0,
+                p.PIN_1,
+                Irqs,
+                PMS5003_TX_BUFFER.init([0; 16]),
+                PMS5003_RX_BUFFER.init([0; 64]),
+                uart_config,
+            );
+            sensors.add(8, Pms5003::new(uart));
+        }
+        temperature_and_humidity::tasks::spawn_tasks(
+            &spawner,
+            sensors,
+            storage::config_partition(flash),
             temp_humidity_channel,
+            device_config_signal,
+            wall_clock,
+            device,
         )
         .await;
     }
@@ -93,5 +216,17 @@ async fn main(spawner: Spawner) {
         p.PIN_29,
         dma::Channel::new(p.DMA_CH0, Irqs),
     );
-    network::controller::run(&spawner, power, spi, led_channel, temp_humidity_channel).await;
+    network::controller::run(
+        &spawner,
+        network::controller::Radio { power, spi },
+        network::controller::Shared {
+            led_channel,
+            temp_humidity_channel,
+            device_config_signal,
+            wall_clock,
+        },
+        storage::queue_partition(flash),
+        device,
+    )
+    .await;
 }
@@ -1,5 +1,8 @@
-use crate::TempHumidityChannel;
+use crate::Measurement;
+use crate::config::sampling::DeviceConfig;
+use crate::device::DeviceId;
 use crate::network::error::SendMeasurementError;
+use crate::network::link::LinkDiagnostics;
 use alloc::format;
 use defmt::{debug, error};
 use embedded_nal_async::{Dns, TcpConnect};
@@ -7,34 +10,74 @@ use reqwless::client::HttpClient;
 use reqwless::headers::ContentType;
 use reqwless::request::{Method, RequestBuilder};
 use reqwless::response::StatusCode;
+use serde::Serialize;
 
 const REST_USER: &str = env!("REST_USER");
 const REST_USER_PASSWORD: &str = env!("REST_USER_PASSWORD");
 const MEASUREMENTS_ENDPOINT: &str = env!("MEASUREMENTS_ENDPOINT");
+const CONFIG_ENDPOINT: &str = "/api/config";
+const DIAGNOSTICS_ENDPOINT: &str = "/api/diagnostics";
+const BATCH_PATH: &str = "/batch";
 
 const TCP_RX_SIZE: usize = 4096;
 
-pub async fn post_measurement<T, D>(
+// Sends several measurements in a single request.
+pub async fn send_measurements<T, D>(
     http_client: &mut HttpClient<'_, T, D>,
     url: &str,
-    temp_humidity_channel: &'static TempHumidityChannel,
+    measurements: &[Measurement],
 ) -> Result<StatusCode, SendMeasurementError>
 where
     T: TcpConnect,
     D: Dns,
 {
-    let measurement = temp_humidity_channel.receive().await;
-    match serde_json_core::to_string::<_, TCP_RX_SIZE>(&measurement) {
+    post_json(
+        http_client,
+        format!("{}{}{}", url, MEASUREMENTS_ENDPOINT, BATCH_PATH).as_str(),
+        measurements,
+    )
+    .await
+}
+
+#[derive(Serialize)]
+struct DiagnosticsReport<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device: Option<DeviceId>,
+    link: &'a LinkDiagnostics,
+}
+
+pub async fn send_diagnostics<T, D>(
+    http_client: &mut HttpClient<'_, T, D>,
+    url: &str,
+    device: Option<DeviceId>,
+    link: &LinkDiagnostics,
+) -> Result<StatusCode, SendMeasurementError>
+where
+    T: TcpConnect,
+    D: Dns,
+{
+    post_json(
+        http_client,
+        format!("{}{}", url, DIAGNOSTICS_ENDPOINT).as_str(),
+        &DiagnosticsReport { device, link },
+    )
+    .await
+}
+
+async fn post_json<T, D, S>(
+    http_client: &mut HttpClient<'_, T, D>,
+    url: &str,
+    payload: &S,
+) -> Result<StatusCode, SendMeasurementError>
+where
+    T: TcpConnect,
+    D: Dns,
+    S: Serialize + ?Sized,
+{
+    match serde_json_core::to_string::<_, TCP_RX_SIZE>(payload) {
         Ok(body) => {
             debug!("Going to post: {}", body.as_str());
-            http_post(
-                http_client,
-                format!("{}{}", url, MEASUREMENTS_ENDPOINT).as_str(),
-                REST_USER,
-                REST_USER_PASSWORD,
-                &body,
-            )
-            .await
+            http_post(http_client, url, REST_USER, REST_USER_PASSWORD, &body).await
         }
         Err(err) => {
             error!(
@@ -46,6 +89,34 @@ where
     }
 }
 
+// The server falls back to its default config for a device it has none for, or when no device
+// is given.
+pub async fn get_device_config<T, D>(
+    http_client: &mut HttpClient<'_, T, D>,
+    url: &str,
+    device: Option<DeviceId>,
+) -> Result<DeviceConfig, SendMeasurementError>
+where
+    T: TcpConnect,
+    D: Dns,
+{
+    let mut rx_buffer = [0; TCP_RX_SIZE];
+    let url = match device {
+        Some(device) => format!("{}{}?device={:016x}", url, CONFIG_ENDPOINT, device.value()),
+        None => format!("{}{}", url, CONFIG_ENDPOINT),
+    };
+    let mut request = http_client.request(Method::GET, url.as_str()).await?;
+    let response = request.send(&mut rx_buffer).await?;
+    if !response.status.is_successful() {
+        return Err(SendMeasurementError::HttpStatus(response.status.0));
+    }
+    let body = response.body().read_to_end().await?;
+    DeviceConfig::from_json(body).map_err(|err| {
+        error!("Device config deserialization failed with: {}", err);
+        SendMeasurementError::DeserializationError
+    })
+}
+
 async fn http_post<T, D>(
     http_client: &mut HttpClient<'_, T, D>,
     url: &str,
@@ -0,0 +1,86 @@
+use alloc::vec::Vec;
+use embassy_time::{Duration, Instant};
+
+use crate::Measurement;
+use crate::clock::WallClock;
+
+pub const BATCH_SIZE: usize = 8;
+pub const BATCH_DEADLINE: Duration = Duration::from_secs(60);
+
+// Measurements collected for a single upload, the radio stays off in between.
+// A batch is ready once it is full or its oldest measurement has waited for the deadline.
+pub struct Batch {
+    measurements: Vec<Measurement>,
+    // when each of the measurements was pushed
+    pushed: Vec<Instant>,
+    size: usize,
+    max_wait: Duration,
+    started: Option<Instant>,
+}
+
+impl Batch {
+    pub fn new(size: usize, max_wait: Duration) -> Self {
+        Batch {
+            measurements: Vec::with_capacity(size),
+            pushed: Vec::with_capacity(size),
+            size,
+            max_wait,
+            started: None,
+        }
+    }
+
+    pub fn push(&mut self, measurement: Measurement, at: Instant) {
+        if self.measurements.is_empty() {
+            self.started = Some(at);
+        }
+        self.measurements.push(measurement);
+        self.pushed.push(at);
+    }
+
+    // When the batch has to be sent even if it isn't full, none for an empty batch.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.started.map(|started| started + self.max_wait)
+    }
+
+    pub fn is_ready(&self, now: Instant) -> bool {
+        self.measurements.len() >= self.size || self.deadline().is_some_and(|at| now >= at)
+    }
+
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    // The measurements with the uptime they were pushed at, which is about when they were taken.
+    pub fn entries(&self) -> impl Iterator<Item = (&Measurement, Instant)> {
+        self.measurements.iter().zip(self.pushed.iter().copied())
+    }
+
+    // Gives the measurements taken before the clock was set their wall-clock time.
+    pub fn backfill(&mut self, wall_clock: &WallClock) {
+        for (measurement, &pushed) in self.measurements.iter_mut().zip(&self.pushed) {
+            if measurement.timestamp.is_none() {
+                measurement.timestamp = wall_clock.at(pushed);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.measurements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.measurements.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.measurements.clear();
+        self.pushed.clear();
+        self.started = None;
+    }
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self::new(BATCH_SIZE, BATCH_DEADLINE)
+    }
+}
@@ -1,22 +1,34 @@
+use alloc::vec::Vec;
 use cyw43::JoinError;
 use cyw43::JoinOptions;
 use cyw43::aligned_bytes;
 use cyw43_pio::PioSpi;
 use defmt::{debug, error, info, warn};
 use embassy_executor::Spawner;
-use embassy_futures::select::select;
+use embassy_futures::select::{Either, Either3, select, select3};
 use embassy_net::dns::DnsSocket;
 use embassy_net::tcp::client::{TcpClient, TcpClientState};
-use embassy_net::{Config, StackResources};
+use embassy_net::{Config, Stack, StackResources};
 use embassy_rp::clocks::RoscRng;
 use embassy_rp::gpio::Output;
+use embassy_time::{Duration, Instant, Timer, with_timeout};
 use reqwless::client::HttpClient;
 use reqwless::response::StatusCode;
 use static_cell::StaticCell;
 
+use crate::DeviceConfigSignal;
 use crate::LedChannel;
+use crate::Measurement;
 use crate::TempHumidityChannel;
+use crate::clock::WallClock;
+use crate::config::storage::{self, FlashPartition};
+use crate::device::DeviceId;
 use crate::network::api;
+use crate::network::batch::{BATCH_SIZE, Batch};
+use crate::network::error::SendMeasurementError;
+use crate::network::link::{Backoff, LinkDiagnostics, LinkLoss};
+use crate::network::sntp;
+use crate::queue::MeasurementQueue;
 
 const TCP_TX_SIZE: usize = 4096;
 const TCP_RX_SIZE: usize = TCP_TX_SIZE;
@@ -24,8 +36,17 @@ const TCP_RX_SIZE: usize = TCP_TX_SIZE;
 const WIFI_NETWORK: &str = env!("WIFI_NETWORK");
 const WIFI_PASSWORD: &str = env!("WIFI_PASSWORD");
 const MEASUREMENTS_SERVER_URL: &str = env!("MEASUREMENTS_SERVER_URL");
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(60);
+const SNTP_SERVER: &str = "pool.ntp.org";
+// The crystal drifts a few seconds a day at most.
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const CLOCK_SYNC_INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+const CLOCK_SYNC_MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+const DHCP_TIMEOUT: Duration = Duration::from_secs(30);
+const BLINK_INTERVAL: Duration = Duration::from_millis(250);
 
 type TcpHttpClient<'a> = HttpClient<'a, TcpClient<'a, 1, TCP_TX_SIZE, TCP_RX_SIZE>, DnsSocket<'a>>;
+type FlashQueue = MeasurementQueue<FlashPartition>;
 
 // Program metadata for `picotool info`.
 // This isn't needed, but it's recommended to have these minimal entries.
@@ -43,22 +64,55 @@ pub static PICOTOOL_ENTRIES: [embassy_rp::binary_info::EntryAddr; 4] = [
 
 static STATE: StaticCell<cyw43::State> = StaticCell::new();
 type Pio = embassy_rp::peripherals::PIO1;
-type WifiPioSpi = PioSpi<'static, Pio, 0>;
+pub type WifiPioSpi = PioSpi<'static, Pio, 0>;
+
+// The pins of the cyw43 wireless chip.
+pub struct Radio {
+    pub power: Output<'static>,
+    pub spi: WifiPioSpi,
+}
+
+// The statics the controller shares with the other tasks.
+pub struct Shared {
+    pub led_channel: &'static LedChannel,
+    pub temp_humidity_channel: &'static TempHumidityChannel,
+    pub device_config_signal: &'static DeviceConfigSignal,
+    pub wall_clock: &'static WallClock,
+}
 
 pub async fn run(
     spawner: &Spawner,
-    power: Output<'static>,
-    spi: WifiPioSpi,
-    led_channel: &'static LedChannel,
-    temp_humidity_channel: &'static TempHumidityChannel,
+    radio: Radio,
+    shared: Shared,
+    queue_flash: FlashPartition,
+    device: Option<DeviceId>,
 ) {
+    let Shared {
+        led_channel,
+        temp_humidity_channel,
+        device_config_signal,
+        wall_clock,
+    } = shared;
+    // Without a queue the measurements that can't be sent right away are dropped.
+    let mut queue = match MeasurementQueue::new(queue_flash, 0, storage::QUEUE_SIZE) {
+        Ok(queue) => {
+            info!("{} queued measurements from before the boot", queue.len());
+            Some(queue)
+        }
+        Err(err) => {
+            error!("Opening the measurement queue failed with: {}", err);
+            None
+        }
+    };
+
     let firmware = aligned_bytes!("../../cyw43-firmware/43439A0.bin");
     // Country Locale Matrix
     let clm = aligned_bytes!("../../cyw43-firmware/43439A0_clm.bin");
     let nvram = aligned_bytes!("../../cyw43-firmware/nvram_rp2040.bin");
 
     let state = STATE.init(cyw43::State::new());
-    let (net_device, mut control, runner) = cyw43::new(state, power, spi, firmware, nvram).await;
+    let (net_device, mut control, runner) =
+        cyw43::new(state, radio.power, radio.spi, firmware, nvram).await;
 
     spawner.spawn(cyw43_task(runner).unwrap());
 
@@ -82,23 +136,25 @@ pub async fn run(
 
     spawner.spawn(net_task(runner).unwrap());
 
+    let mut backoff = Backoff::default();
+    let mut diagnostics = LinkDiagnostics::default();
+    // Kept outside the loop so a LED update doesn't lose the collected measurements.
+    let mut batch = Batch::default();
+    // The last state sent by the game, shown while the network is up.
+    let mut led_state = false;
     info!("try to join the network...");
-    while let Err(err) = control
-        .join(WIFI_NETWORK, JoinOptions::new(WIFI_PASSWORD.as_bytes()))
-        .await
-    {
-        log_join_errror(err);
-    }
-
-    info!("waiting for link...");
-    stack.wait_link_up().await;
-
-    info!("waiting for DHCP...");
-    stack.wait_config_up().await;
-
-    info!("Stack is up!");
-    // Activate the led to signal that the stack is up.
-    control.gpio_set(0, true).await;
+    select(
+        connect(
+            &mut control,
+            stack,
+            led_channel,
+            &mut led_state,
+            &mut backoff,
+            &mut diagnostics,
+        ),
+        persist_measurements(temp_humidity_channel, &mut batch, &mut queue),
+    )
+    .await;
 
     let client_state = TcpClientState::<1, TCP_TX_SIZE, TCP_RX_SIZE>::new();
     let tcp_client = TcpClient::new(stack, &client_state);
@@ -106,24 +162,330 @@ pub async fn run(
 
     let mut http_client = HttpClient::new(&tcp_client, &dns_client);
 
+    let mut last_config_fetch: Option<Instant> = None;
+    let mut reported_diagnostics: Option<LinkDiagnostics> = None;
+    let mut next_clock_sync = Instant::now();
+    let mut clock_sync_backoff = Backoff::new(CLOCK_SYNC_INITIAL_BACKOFF, CLOCK_SYNC_MAX_BACKOFF);
     loop {
-        select(
-            set_led_state(&mut control, led_channel),
-            post_measurement(&mut http_client, temp_humidity_channel),
+        if Instant::now() >= next_clock_sync {
+            let delay = if sync_clock(stack, wall_clock).await {
+                clock_sync_backoff.reset();
+                CLOCK_SYNC_INTERVAL
+            } else {
+                clock_sync_backoff.next_delay()
+            };
+            next_clock_sync = Instant::now() + delay;
+        }
+        // The config is fetched between requests, at most once per poll interval.
+        // Changed diagnostics are reported along with it.
+        if last_config_fetch.is_none_or(|at| at.elapsed() >= CONFIG_POLL_INTERVAL) {
+            fetch_device_config(&mut http_client, device, device_config_signal).await;
+            if reported_diagnostics != Some(diagnostics)
+                && report_diagnostics(&mut http_client, device, &diagnostics).await
+            {
+                reported_diagnostics = Some(diagnostics);
+            }
+            last_config_fetch = Some(Instant::now());
+        }
+        // Only waits that can be cancelled safely race here, an upload always runs to its end.
+        match select3(
+            collect(temp_humidity_channel, &mut batch),
+            led_channel.receive(),
+            connection_lost(stack),
         )
-        .await;
+        .await
+        {
+            Either3::First(()) => {
+                if batch.is_ready(Instant::now()) {
+                    upload(&mut http_client, &mut batch, &mut queue, wall_clock).await;
+                }
+            }
+            Either3::Second(state) => {
+                led_state = state;
+                control.gpio_set(0, led_state).await;
+            }
+            Either3::Third(loss) => {
+                diagnostics.lost(loss);
+                warn!("Connection lost: {}", loss);
+                control.gpio_set(0, false).await;
+                select(
+                    reconnect(
+                        &mut control,
+                        stack,
+                        led_channel,
+                        &mut led_state,
+                        loss,
+                        &mut backoff,
+                        &mut diagnostics,
+                    ),
+                    persist_measurements(temp_humidity_channel, &mut batch, &mut queue),
+                )
+                .await;
+                diagnostics.reconnected();
+                info!("Reconnected, {}", diagnostics);
+            }
+        }
+    }
+}
+
+// Joins the network and waits for a DHCP lease, backing off between failed attempts.
+async fn connect(
+    control: &mut cyw43::Control<'static>,
+    stack: Stack<'_>,
+    led_channel: &'static LedChannel,
+    led_state: &mut bool,
+    backoff: &mut Backoff,
+    diagnostics: &mut LinkDiagnostics,
+) {
+    loop {
+        match control
+            .join(WIFI_NETWORK, JoinOptions::new(WIFI_PASSWORD.as_bytes()))
+            .await
+        {
+            Ok(()) => {
+                info!("waiting for DHCP...");
+                if with_timeout(DHCP_TIMEOUT, stack.wait_config_up())
+                    .await
+                    .is_ok()
+                {
+                    break;
+                }
+                warn!("no DHCP lease within {} s", DHCP_TIMEOUT.as_secs());
+                control.leave().await;
+            }
+            Err(err) => log_join_errror(err),
+        }
+        diagnostics.failed_join();
+        let delay = backoff.next_delay();
+        info!("joining again in {} s, {}", delay.as_secs(), diagnostics);
+        blink_until(control, led_channel, led_state, Instant::now() + delay).await;
+    }
+    backoff.reset();
+
+    info!("Stack is up!");
+    // The LED stops blinking and shows the game again once the stack is up.
+    control.gpio_set(0, *led_state).await;
+}
+
+async fn reconnect(
+    control: &mut cyw43::Control<'static>,
+    stack: Stack<'_>,
+    led_channel: &'static LedChannel,
+    led_state: &mut bool,
+    loss: LinkLoss,
+    backoff: &mut Backoff,
+    diagnostics: &mut LinkDiagnostics,
+) {
+    // The DHCP client renews a lost lease by itself as long as the link is up.
+    let renewed = loss == LinkLoss::Lease
+        && with_timeout(DHCP_TIMEOUT, stack.wait_config_up())
+            .await
+            .is_ok();
+    if renewed {
+        control.gpio_set(0, *led_state).await;
+    } else {
+        control.leave().await;
+        connect(control, stack, led_channel, led_state, backoff, diagnostics).await;
+    }
+}
+
+// Resolves once the association or the DHCP lease is lost.
+async fn connection_lost(stack: Stack<'_>) -> LinkLoss {
+    match select(stack.wait_link_down(), stack.wait_config_down()).await {
+        Either::First(()) => LinkLoss::Link,
+        Either::Second(()) => LinkLoss::Lease,
+    }
+}
+
+// The LED blinks while the network is down, the last LED state sent in the meantime is kept
+// for when it is back up.
+async fn blink_until(
+    control: &mut cyw43::Control<'static>,
+    led_channel: &'static LedChannel,
+    led_state: &mut bool,
+    until: Instant,
+) {
+    let mut blink_state = false;
+    while Instant::now() < until {
+        blink_state = !blink_state;
+        control.gpio_set(0, blink_state).await;
+        let next = (Instant::now() + BLINK_INTERVAL).min(until);
+        while let Either::Second(state) = select(Timer::at(next), led_channel.receive()).await {
+            *led_state = state;
+        }
     }
+    control.gpio_set(0, false).await;
 }
 
-async fn post_measurement(
+// The sensor task applies the config, it owns the sampling and the calibration.
+async fn fetch_device_config(
     http_client: &mut TcpHttpClient<'_>,
+    device: Option<DeviceId>,
+    device_config_signal: &'static DeviceConfigSignal,
+) {
+    match api::get_device_config(http_client, MEASUREMENTS_SERVER_URL, device).await {
+        Ok(config) => device_config_signal.signal(config),
+        Err(err) => warn!("Fetching the device config failed with: {}", err),
+    }
+}
+
+// Returns true if the server accepted the diagnostics.
+async fn report_diagnostics(
+    http_client: &mut TcpHttpClient<'_>,
+    device: Option<DeviceId>,
+    diagnostics: &LinkDiagnostics,
+) -> bool {
+    match api::send_diagnostics(http_client, MEASUREMENTS_SERVER_URL, device, diagnostics).await {
+        Ok(status_code) if status_code.is_successful() => true,
+        Ok(status_code) => {
+            warn!(
+                "Reporting the diagnostics failed with http exit code: {}",
+                status_code.0
+            );
+            false
+        }
+        Err(err) => {
+            warn!("Reporting the diagnostics failed with: {}", err);
+            false
+        }
+    }
+}
+
+// Returns true if the clock was set.
+async fn sync_clock(stack: Stack<'_>, wall_clock: &'static WallClock) -> bool {
+    match sntp::fetch_time(stack, SNTP_SERVER, RoscRng.next_u64()).await {
+        Ok((unix_millis, at)) => {
+            wall_clock.set(unix_millis, at);
+            info!(
+                "Clock synchronised to {} ms since the Unix epoch",
+                unix_millis
+            );
+            true
+        }
+        Err(err) => {
+            warn!("Synchronising the clock failed with: {}", err);
+            false
+        }
+    }
+}
+
+// Adds the next measurement to the batch, returns early at the deadline of the batch.
+async fn collect(temp_humidity_channel: &'static TempHumidityChannel, batch: &mut Batch) {
+    let received = match batch.deadline() {
+        Some(deadline) => {
+            match select(temp_humidity_channel.receive(), Timer::at(deadline)).await {
+                Either::First(measurement) => Some(measurement),
+                Either::Second(()) => None,
+            }
+        }
+        None => Some(temp_humidity_channel.receive().await),
+    };
+    if let Some(measurement) = received {
+        batch.push(measurement, Instant::now());
+    }
+}
+
+// Sends a ready batch and whatever is queued behind it.
+async fn upload(
+    http_client: &mut TcpHttpClient<'_>,
+    batch: &mut Batch,
+    queue: &mut Option<FlashQueue>,
+    wall_clock: &'static WallClock,
+) {
+    batch.backfill(wall_clock);
+
+    // A new batch waits behind the queued measurements to keep them in order.
+    let queued = queue.as_ref().is_some_and(|queue| !queue.is_empty());
+    if queued || !deliver(http_client, batch.measurements()).await {
+        queue_batch(batch, queue);
+    }
+    batch.clear();
+    if let Some(queue) = queue {
+        replay(http_client, queue, wall_clock).await;
+    }
+}
+
+// Keeps draining the channel while the network is down, so the sensor task doesn't block.
+// Ready batches go to the queue and are sent once the network is back.
+async fn persist_measurements(
     temp_humidity_channel: &'static TempHumidityChannel,
-) -> () {
-    match api::post_measurement(http_client, MEASUREMENTS_SERVER_URL, temp_humidity_channel).await {
-        Ok(status_code) => handle_status_code(status_code),
-        Err(err) => error!("Posting measurement failed with: {}", err),
-        #[allow(unreachable_patterns)]
-        _ => unreachable!(),
+    batch: &mut Batch,
+    queue: &mut Option<FlashQueue>,
+) {
+    loop {
+        collect(temp_humidity_channel, batch).await;
+        if batch.is_ready(Instant::now()) {
+            queue_batch(batch, queue);
+            batch.clear();
+        }
+    }
+}
+
+fn queue_batch(batch: &Batch, queue: &mut Option<FlashQueue>) {
+    let Some(queue) = queue else {
+        warn!("{} measurements dropped without a queue", batch.len());
+        return;
+    };
+    for (measurement, pushed) in batch.entries() {
+        if let Err(err) = queue.push(measurement, pushed.as_millis()) {
+            error!("Queueing measurement failed with: {}", err);
+        }
+    }
+    info!("{} measurements queued", queue.len());
+    if queue.dropped() > 0 {
+        warn!("{} queued measurements dropped", queue.dropped());
+    }
+}
+
+// Sends the queued measurements oldest first, a batch at a time, until one of them fails.
+async fn replay(
+    http_client: &mut TcpHttpClient<'_>,
+    queue: &mut FlashQueue,
+    wall_clock: &'static WallClock,
+) {
+    while !queue.is_empty() {
+        let count = queue.len().min(BATCH_SIZE as u32);
+        let mut measurements = Vec::with_capacity(count as usize);
+        let mut skipped = 0;
+        for index in 0..count {
+            match queue.get(index) {
+                Some(Ok(mut queued)) => {
+                    queued.backfill(wall_clock);
+                    measurements.push(queued.measurement);
+                }
+                Some(Err(err)) => {
+                    warn!("Skipping unreadable queued measurement: {}", err);
+                    skipped += 1;
+                }
+                None => break,
+            }
+        }
+        if !measurements.is_empty() && !deliver(http_client, &measurements).await {
+            break;
+        }
+        // Only what was read, entries after an early end stay queued.
+        for _ in 0..measurements.len() as u32 + skipped {
+            if let Err(err) = queue.pop() {
+                error!("Removing queued measurement failed with: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+// Returns false if the measurements should be sent again later.
+async fn deliver(http_client: &mut TcpHttpClient<'_>, measurements: &[Measurement]) -> bool {
+    match api::send_measurements(http_client, MEASUREMENTS_SERVER_URL, measurements).await {
+        Ok(status_code) => {
+            handle_status_code(status_code);
+            !status_code.is_server_error()
+        }
+        // Sending them again won't help.
+        Err(SendMeasurementError::SerializationError) => true,
+        Err(err) => {
+            error!("Posting measurements failed with: {}", err);
+            false
+        }
     }
 }
 
@@ -154,11 +516,6 @@ fn log_join_errror(err: JoinError) {
     }
 }
 
-async fn set_led_state(control: &mut cyw43::Control<'static>, led_channel: &'static LedChannel) {
-    let led_state = led_channel.receive().await;
-    control.gpio_set(0, led_state).await;
-}
-
 #[embassy_executor::task]
 async fn cyw43_task(
     runner: cyw43::Runner<'static, cyw43::SpiBus<Output<'static>, WifiPioSpi>>,
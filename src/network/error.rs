@@ -2,6 +2,8 @@
 pub enum SendMeasurementError {
     ReqwlessError(reqwless::Error),
     SerializationError,
+    DeserializationError,
+    HttpStatus(u16),
 }
 
 impl defmt::Format for SendMeasurementError {
@@ -33,6 +35,10 @@ impl defmt::Format for SendMeasurementError {
             Self::SerializationError => {
                 defmt::write!(fmt, "{}", "SerializationError")
             }
+            Self::DeserializationError => {
+                defmt::write!(fmt, "{}", "DeserializationError")
+            }
+            Self::HttpStatus(status) => defmt::write!(fmt, "HttpStatus({})", status),
         }
     }
 }
@@ -42,3 +48,22 @@ impl From<reqwless::Error> for SendMeasurementError {
         Self::ReqwlessError(err)
     }
 }
+
+#[derive(Debug, PartialEq)]
+pub enum SntpError {
+    DnsError,
+    SocketError,
+    TimeoutError,
+    InvalidResponse,
+}
+
+impl defmt::Format for SntpError {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match self {
+            Self::DnsError => defmt::write!(fmt, "{}", "DnsError"),
+            Self::SocketError => defmt::write!(fmt, "{}", "SocketError"),
+            Self::TimeoutError => defmt::write!(fmt, "{}", "TimeoutError"),
+            Self::InvalidResponse => defmt::write!(fmt, "{}", "InvalidResponse"),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use embassy_time::Duration;
+use serde::Serialize;
+
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkLoss {
+    // The access point disappeared or dropped the association.
+    Link,
+    // The association is still up but the DHCP lease ran out.
+    Lease,
+}
+
+impl defmt::Format for LinkLoss {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match self {
+            Self::Link => defmt::write!(fmt, "{}", "Link"),
+            Self::Lease => defmt::write!(fmt, "{}", "Lease"),
+        }
+    }
+}
+
+// Delays between attempts to join the network, doubling after every failure.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(INITIAL_BACKOFF, MAX_BACKOFF)
+    }
+}
+
+// Counters for the health of the WiFi connection since boot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct LinkDiagnostics {
+    pub reconnects: u32,
+    pub link_losses: u32,
+    pub lease_losses: u32,
+    pub failed_joins: u32,
+}
+
+impl LinkDiagnostics {
+    pub fn lost(&mut self, loss: LinkLoss) {
+        match loss {
+            LinkLoss::Link => self.link_losses += 1,
+            LinkLoss::Lease => self.lease_losses += 1,
+        }
+    }
+
+    pub fn failed_join(&mut self) {
+        self.failed_joins += 1;
+    }
+
+    pub fn reconnected(&mut self) {
+        self.reconnects += 1;
+    }
+}
+
+impl defmt::Format for LinkDiagnostics {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        defmt::write!(
+            fmt,
+            "reconnects={} link_losses={} lease_losses={} failed_joins={}",
+            self.reconnects,
+            self.link_losses,
+            self.lease_losses,
+            self.failed_joins
+        )
+    }
+}
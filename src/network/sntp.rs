@@ -0,0 +1,117 @@
+use embassy_net::dns::DnsQueryType;
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{IpEndpoint, Stack};
+use embassy_time::{Duration, Instant, with_timeout};
+
+use crate::network::error::SntpError;
+
+pub const NTP_PORT: u16 = 123;
+pub const PACKET_LEN: usize = 48;
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+// Seconds from the NTP epoch (1900) to the Unix epoch (1970)
+const UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+const LEAP_NOT_SYNCHRONISED: u8 = 3;
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+
+// The server copies `transmit` into the originate time of its response.
+pub fn request(transmit: u64) -> [u8; PACKET_LEN] {
+    let mut packet = [0; PACKET_LEN];
+    packet[0] = (VERSION << 3) | MODE_CLIENT;
+    packet[40..48].copy_from_slice(&transmit.to_be_bytes());
+    packet
+}
+
+// The transmit time of the response to the request sent with `transmit`,
+// in milliseconds since the Unix epoch.
+pub fn parse_response(packet: &[u8], transmit: u64) -> Result<u64, SntpError> {
+    if packet.len() < PACKET_LEN {
+        return Err(SntpError::InvalidResponse);
+    }
+    let leap = packet[0] >> 6;
+    let mode = packet[0] & 0b111;
+    let stratum = packet[1];
+    // Stratum 0 is a kiss-o'-death, telling the client to back off.
+    if leap == LEAP_NOT_SYNCHRONISED || mode != MODE_SERVER || stratum == 0 || stratum > 15 {
+        return Err(SntpError::InvalidResponse);
+    }
+    // Anything else answers an other request or is spoofed.
+    if packet[24..32] != transmit.to_be_bytes() {
+        return Err(SntpError::InvalidResponse);
+    }
+    let seconds = u32::from_be_bytes([packet[40], packet[41], packet[42], packet[43]]) as u64;
+    let fraction = u32::from_be_bytes([packet[44], packet[45], packet[46], packet[47]]) as u64;
+    if seconds == 0 {
+        return Err(SntpError::InvalidResponse);
+    }
+    // The seconds wrap in 2036, times with the top bit cleared are from the next era.
+    let seconds = if seconds & 0x8000_0000 == 0 {
+        seconds + (1 << 32)
+    } else {
+        seconds
+    };
+    let unix_seconds = seconds
+        .checked_sub(UNIX_EPOCH_OFFSET)
+        .ok_or(SntpError::InvalidResponse)?;
+    Ok(unix_seconds * 1000 + ((fraction * 1000) >> 32))
+}
+
+// Asks `server` for the time, returns the Unix time in milliseconds and the instant it applies to.
+// `transmit` should be random, it ties the response to the request.
+pub async fn fetch_time(
+    stack: Stack<'_>,
+    server: &str,
+    transmit: u64,
+) -> Result<(u64, Instant), SntpError> {
+    let address = stack
+        .dns_query(server, DnsQueryType::A)
+        .await
+        .map_err(|_| SntpError::DnsError)?
+        .first()
+        .copied()
+        .ok_or(SntpError::DnsError)?;
+
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0; PACKET_LEN];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; PACKET_LEN];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    socket.bind(0).map_err(|_| SntpError::SocketError)?;
+
+    let endpoint = IpEndpoint::new(address, NTP_PORT);
+    let sent = Instant::now();
+    socket
+        .send_to(&request(transmit), endpoint)
+        .await
+        .map_err(|_| SntpError::SocketError)?;
+    let mut response = [0; PACKET_LEN];
+    let len = with_timeout(RESPONSE_TIMEOUT, async {
+        loop {
+            let (len, meta) = socket
+                .recv_from(&mut response)
+                .await
+                .map_err(|_| SntpError::SocketError)?;
+            // Datagrams from anywhere but the server are ignored.
+            if meta.endpoint == endpoint {
+                return Ok::<_, SntpError>(len);
+            }
+        }
+    })
+    .await
+    .map_err(|_| SntpError::TimeoutError)??;
+    let received = Instant::now();
+
+    // The server's time is taken halfway through the round trip.
+    let unix_millis =
+        parse_response(&response[..len], transmit)? + (received - sent).as_millis() / 2;
+    Ok((unix_millis, received))
+}
@@ -0,0 +1,116 @@
+use alloc::collections::BTreeMap;
+use embassy_time::{Duration, Instant};
+
+use crate::Measurement;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PublishConfig {
+    // Smallest change in °C compared to the last published measurement that is sent.
+    pub temperature_deadband: f32,
+    // Smallest change in % compared to the last published measurement that is sent.
+    pub humidity_deadband: f32,
+    // Smallest change in hPa compared to the last published measurement that is sent.
+    pub pressure_deadband: f32,
+    // Smallest change in ppm compared to the last published measurement that is sent.
+    pub co2_deadband: u16,
+    // Longest time without a published measurement of a sensor.
+    pub heartbeat: Duration,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            temperature_deadband: 0.2,
+            humidity_deadband: 1.0,
+            pressure_deadband: 0.5,
+            co2_deadband: 20,
+            heartbeat: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Published {
+    temperature: Option<f32>,
+    humidity: Option<f32>,
+    pressure: Option<f32>,
+    co2_ppm: Option<u16>,
+    at: Instant,
+}
+
+// Decides for every measurement whether it is worth sending. Sensors and DS18B20 probes
+// are tracked separately, measurements without a value with a deadband, e.g. particulate
+// matter, are always sent.
+#[derive(Clone, Debug, Default)]
+pub struct PublishPolicy {
+    config: PublishConfig,
+    published: BTreeMap<(u8, Option<u64>), Published>,
+}
+
+impl PublishPolicy {
+    pub fn new(config: PublishConfig) -> Self {
+        PublishPolicy {
+            config,
+            published: BTreeMap::new(),
+        }
+    }
+
+    // Remembers the measurement as published if it should be sent.
+    pub fn should_publish(&mut self, measurement: &Measurement, at: Instant) -> bool {
+        if measurement.temperature.is_none()
+            && measurement.humidity.is_none()
+            && measurement.pressure.is_none()
+            && measurement.co2_ppm.is_none()
+        {
+            return true;
+        }
+        let key = (measurement.sensor, measurement.probe);
+        let publish = match self.published.get(&key) {
+            Some(published) => {
+                at.saturating_duration_since(published.at) >= self.config.heartbeat
+                    || changed(
+                        published.temperature,
+                        measurement.temperature,
+                        self.config.temperature_deadband,
+                    )
+                    || changed(
+                        published.humidity,
+                        measurement.humidity,
+                        self.config.humidity_deadband,
+                    )
+                    || changed(
+                        published.pressure,
+                        measurement.pressure,
+                        self.config.pressure_deadband,
+                    )
+                    || changed(
+                        published.co2_ppm.map(f32::from),
+                        measurement.co2_ppm.map(f32::from),
+                        f32::from(self.config.co2_deadband),
+                    )
+            }
+            None => true,
+        };
+        if publish {
+            self.published.insert(
+                key,
+                Published {
+                    temperature: measurement.temperature,
+                    humidity: measurement.humidity,
+                    pressure: measurement.pressure,
+                    co2_ppm: measurement.co2_ppm,
+                    at,
+                },
+            );
+        }
+        publish
+    }
+}
+
+fn changed(published: Option<f32>, current: Option<f32>, deadband: f32) -> bool {
+    match (published, current) {
+        (Some(published), Some(current)) => (current - published).abs() >= deadband,
+        (None, None) => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,303 @@
+use embassy_time::Instant;
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Measurement;
+use crate::checksum::fletcher16;
+use crate::clock::WallClock;
+use crate::device::DeviceId;
+
+pub const RECORD_LEN: usize = 64;
+
+// A record is written once and later marked as sent by clearing its state byte,
+// which NOR flash allows without erasing the sector.
+const PENDING: u8 = 0xFF;
+const SENT: u8 = 0x00;
+const EMPTY_SEQUENCE: u32 = u32::MAX;
+
+const PROBE: u16 = 1 << 0;
+const HUMIDITY: u16 = 1 << 1;
+const TEMPERATURE: u16 = 1 << 2;
+const PRESSURE: u16 = 1 << 3;
+const CO2_PPM: u16 = 1 << 4;
+const PM1_0: u16 = 1 << 5;
+const PM2_5: u16 = 1 << 6;
+const PM10: u16 = 1 << 7;
+const CALIBRATION: u16 = 1 << 8;
+const TIMESTAMP: u16 = 1 << 9;
+const DEVICE: u16 = 1 << 10;
+
+#[derive(Debug, PartialEq)]
+pub enum QueueError {
+    FlashError,
+    ChecksumError,
+}
+
+impl defmt::Format for QueueError {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match self {
+            Self::FlashError => defmt::write!(fmt, "{}", "FlashError"),
+            Self::ChecksumError => defmt::write!(fmt, "{}", "ChecksumError"),
+        }
+    }
+}
+
+// A measurement waiting to be sent, with the uptime in milliseconds when it was taken.
+#[derive(Clone)]
+pub struct QueuedMeasurement {
+    pub measurement: Measurement,
+    pub timestamp: u64,
+    // The uptime only means something for measurements of the current boot.
+    pub current_boot: bool,
+}
+
+impl QueuedMeasurement {
+    // Gives a measurement taken before the clock was set its wall-clock time.
+    pub fn backfill(&mut self, wall_clock: &WallClock) {
+        if self.measurement.timestamp.is_none() && self.current_boot {
+            self.measurement.timestamp = wall_clock.at(Instant::from_millis(self.timestamp));
+        }
+    }
+}
+
+// A persistent FIFO of unsent measurements in a ring of flash sectors.
+// Records are appended in order, when the ring is full the oldest sector is
+// erased and its pending measurements are dropped.
+pub struct MeasurementQueue<F> {
+    flash: F,
+    offset: u32,
+    slots: u32,
+    next_sequence: u32,
+    // sequence of the first record written since the boot
+    boot_sequence: u32,
+    // slot the next record is written to
+    head: u32,
+    // slot of the oldest pending record
+    tail: u32,
+    len: u32,
+    dropped: u32,
+}
+
+impl<F: NorFlash> MeasurementQueue<F> {
+    // Picks up the records left by an earlier boot, `size` is a multiple of the erase size.
+    pub fn new(mut flash: F, offset: u32, size: u32) -> Result<Self, QueueError> {
+        let slots = size / RECORD_LEN as u32;
+        let mut last: Option<(u32, u32)> = None;
+        let mut oldest_pending: Option<(u32, u32)> = None;
+        for slot in 0..slots {
+            let mut record = [0; RECORD_LEN];
+            flash
+                .read(offset + slot * RECORD_LEN as u32, &mut record)
+                .map_err(|_| QueueError::FlashError)?;
+            let Some(sequence) = sequence(&record) else {
+                continue;
+            };
+            if last.is_none_or(|(last_sequence, _)| sequence > last_sequence) {
+                last = Some((sequence, slot));
+            }
+            if record[0] == PENDING
+                && oldest_pending.is_none_or(|(oldest_sequence, _)| sequence < oldest_sequence)
+            {
+                oldest_pending = Some((sequence, slot));
+            }
+        }
+        let (next_sequence, mut head) = match last {
+            Some((sequence, slot)) => (sequence + 1, (slot + 1) % slots),
+            None => (0, 0),
+        };
+        // A power loss during a write leaves a slot that is neither a record nor erased, writing
+        // over it would corrupt the next record. A sector is erased before its first slot is used.
+        let slots_per_sector = (F::ERASE_SIZE / RECORD_LEN) as u32;
+        while !head.is_multiple_of(slots_per_sector) {
+            let mut record = [0; RECORD_LEN];
+            flash
+                .read(offset + head * RECORD_LEN as u32, &mut record)
+                .map_err(|_| QueueError::FlashError)?;
+            if record.iter().all(|&byte| byte == 0xFF) {
+                break;
+            }
+            head = (head + 1) % slots;
+        }
+        // Records are popped in order, everything from the oldest pending one up to the head is
+        // pending. A torn or corrupted slot in between is popped like any other.
+        let tail = oldest_pending.map_or(head, |(_, slot)| slot);
+        Ok(MeasurementQueue {
+            flash,
+            offset,
+            slots,
+            next_sequence,
+            boot_sequence: next_sequence,
+            head,
+            tail,
+            len: (head + slots - tail) % slots,
+            dropped: 0,
+        })
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Number of pending measurements overwritten because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    pub fn push(&mut self, measurement: &Measurement, timestamp: u64) -> Result<(), QueueError> {
+        let slots_per_sector = (F::ERASE_SIZE / RECORD_LEN) as u32;
+        if self.head.is_multiple_of(slots_per_sector) {
+            let sector_end = self.head + slots_per_sector;
+            if self.len > 0 && (self.head..sector_end).contains(&self.tail) {
+                let dropped = sector_end - self.tail;
+                self.dropped += dropped;
+                self.len -= dropped;
+                self.tail = sector_end % self.slots;
+            }
+            self.flash
+                .erase(
+                    self.address(self.head),
+                    self.address(self.head) + F::ERASE_SIZE as u32,
+                )
+                .map_err(|_| QueueError::FlashError)?;
+        }
+        let record = encode(measurement, timestamp, self.next_sequence);
+        self.flash
+            .write(self.address(self.head), &record)
+            .map_err(|_| QueueError::FlashError)?;
+        if self.len == 0 {
+            self.tail = self.head;
+        }
+        self.head = (self.head + 1) % self.slots;
+        self.next_sequence += 1;
+        self.len += 1;
+        Ok(())
+    }
+
+    // The oldest pending measurement, a corrupted record has to be popped like any other.
+    pub fn peek(&mut self) -> Option<Result<QueuedMeasurement, QueueError>> {
+        self.get(0)
+    }
+
+    // The pending measurement `index` places behind the oldest one.
+    pub fn get(&mut self, index: u32) -> Option<Result<QueuedMeasurement, QueueError>> {
+        if index >= self.len {
+            return None;
+        }
+        let mut record = [0; RECORD_LEN];
+        Some(
+            self.flash
+                .read(self.address((self.tail + index) % self.slots), &mut record)
+                .map_err(|_| QueueError::FlashError)
+                .and_then(|_| decode(&record, self.boot_sequence)),
+        )
+    }
+
+    // Marks the oldest pending measurement as sent.
+    pub fn pop(&mut self) -> Result<(), QueueError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        self.flash
+            .write(self.address(self.tail), &[SENT])
+            .map_err(|_| QueueError::FlashError)?;
+        self.tail = (self.tail + 1) % self.slots;
+        self.len -= 1;
+        Ok(())
+    }
+
+    fn address(&self, slot: u32) -> u32 {
+        self.offset + slot * RECORD_LEN as u32
+    }
+}
+
+// The sequence number of a complete record, erased and partially written slots have none.
+fn sequence(record: &[u8; RECORD_LEN]) -> Option<u32> {
+    let sequence = u32::from_le_bytes([record[1], record[2], record[3], record[4]]);
+    (sequence != EMPTY_SEQUENCE && is_intact(record)).then_some(sequence)
+}
+
+fn is_intact(record: &[u8; RECORD_LEN]) -> bool {
+    fletcher16(&record[1..RECORD_LEN - 2]).to_le_bytes() == record[RECORD_LEN - 2..]
+}
+
+fn encode(measurement: &Measurement, timestamp: u64, sequence: u32) -> [u8; RECORD_LEN] {
+    let mut flags = 0;
+    for (flag, present) in [
+        (PROBE, measurement.probe.is_some()),
+        (HUMIDITY, measurement.humidity.is_some()),
+        (TEMPERATURE, measurement.temperature.is_some()),
+        (PRESSURE, measurement.pressure.is_some()),
+        (CO2_PPM, measurement.co2_ppm.is_some()),
+        (PM1_0, measurement.pm1_0.is_some()),
+        (PM2_5, measurement.pm2_5.is_some()),
+        (PM10, measurement.pm10.is_some()),
+        (CALIBRATION, measurement.calibration.is_some()),
+        (TIMESTAMP, measurement.timestamp.is_some()),
+        (DEVICE, measurement.device.is_some()),
+    ] {
+        if present {
+            flags |= flag;
+        }
+    }
+
+    let mut record = [0; RECORD_LEN];
+    record[0] = PENDING;
+    record[1..5].copy_from_slice(&sequence.to_le_bytes());
+    record[5..13].copy_from_slice(&timestamp.to_le_bytes());
+    record[13] = measurement.sensor;
+    record[14..16].copy_from_slice(&flags.to_le_bytes());
+    record[16..24].copy_from_slice(&measurement.probe.unwrap_or_default().to_le_bytes());
+    record[24..28].copy_from_slice(&measurement.humidity.unwrap_or_default().to_le_bytes());
+    record[28..32].copy_from_slice(&measurement.temperature.unwrap_or_default().to_le_bytes());
+    record[32..36].copy_from_slice(&measurement.pressure.unwrap_or_default().to_le_bytes());
+    record[36..38].copy_from_slice(&measurement.co2_ppm.unwrap_or_default().to_le_bytes());
+    record[38..40].copy_from_slice(&measurement.pm1_0.unwrap_or_default().to_le_bytes());
+    record[40..42].copy_from_slice(&measurement.pm2_5.unwrap_or_default().to_le_bytes());
+    record[42..44].copy_from_slice(&measurement.pm10.unwrap_or_default().to_le_bytes());
+    record[44..46].copy_from_slice(&measurement.calibration.unwrap_or_default().to_le_bytes());
+    record[46..54].copy_from_slice(&measurement.timestamp.unwrap_or_default().to_le_bytes());
+    let device = measurement.device.map_or(0, |device| device.value());
+    record[54..62].copy_from_slice(&device.to_le_bytes());
+    // The state byte changes after writing and isn't covered by the checksum.
+    let checksum = fletcher16(&record[1..RECORD_LEN - 2]);
+    record[RECORD_LEN - 2..].copy_from_slice(&checksum.to_le_bytes());
+    record
+}
+
+fn decode(record: &[u8; RECORD_LEN], boot_sequence: u32) -> Result<QueuedMeasurement, QueueError> {
+    if !is_intact(record) {
+        return Err(QueueError::ChecksumError);
+    }
+    let flags = u16::from_le_bytes([record[14], record[15]]);
+    let u16_at = |i: usize| u16::from_le_bytes([record[i], record[i + 1]]);
+    let f32_at =
+        |i: usize| f32::from_le_bytes([record[i], record[i + 1], record[i + 2], record[i + 3]]);
+    let u64_at = |i: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&record[i..i + 8]);
+        u64::from_le_bytes(bytes)
+    };
+    Ok(QueuedMeasurement {
+        measurement: Measurement {
+            sensor: record[13],
+            probe: (flags & PROBE != 0).then(|| u64_at(16)),
+            humidity: (flags & HUMIDITY != 0).then(|| f32_at(24)),
+            temperature: (flags & TEMPERATURE != 0).then(|| f32_at(28)),
+            pressure: (flags & PRESSURE != 0).then(|| f32_at(32)),
+            co2_ppm: (flags & CO2_PPM != 0).then(|| u16_at(36)),
+            pm1_0: (flags & PM1_0 != 0).then(|| u16_at(38)),
+            pm2_5: (flags & PM2_5 != 0).then(|| u16_at(40)),
+            pm10: (flags & PM10 != 0).then(|| u16_at(42)),
+            // A diagnostic of the moment, the next live measurement has the current count.
+            rejected: None,
+            calibration: (flags & CALIBRATION != 0).then(|| u16_at(44)),
+            timestamp: (flags & TIMESTAMP != 0).then(|| u64_at(46)),
+            device: (flags & DEVICE != 0).then(|| DeviceId::new(u64_at(54))),
+        },
+        timestamp: u64_at(5),
+        current_boot: sequence(record).is_some_and(|sequence| sequence >= boot_sequence),
+    })
+}
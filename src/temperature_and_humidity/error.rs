@@ -1,27 +1,34 @@
-use embassy_dht_rp2350_sensor::DHTSensorError;
+use embassy_dht_rp2350_sensor::{DHTSensorError, SensorError};
 
 #[derive(Clone, Debug)]
-pub enum FormattableDHTSensorError {
-    DHTSensorError(DHTSensorError),
+pub enum FormattableSensorError {
+    SensorError(SensorError),
 }
 
-impl defmt::Format for FormattableDHTSensorError {
+impl defmt::Format for FormattableSensorError {
     fn format(&self, fmt: defmt::Formatter<'_>) {
         match self {
-            Self::DHTSensorError(DHTSensorError::NoData) => defmt::write!(fmt, "{}", "NoData"),
-            Self::DHTSensorError(DHTSensorError::ChecksumError) => {
+            Self::SensorError(SensorError::NoData) => defmt::write!(fmt, "{}", "NoData"),
+            Self::SensorError(SensorError::ChecksumError) => {
                 defmt::write!(fmt, "{}", "ChecksumError")
             }
-            Self::DHTSensorError(DHTSensorError::InvalidData) => {
-                defmt::write!(fmt, "{}", "InvalidData")
-            }
-            Self::DHTSensorError(DHTSensorError::Timeout) => defmt::write!(fmt, "{}", "Timeout"),
+            Self::SensorError(SensorError::InvalidData) => defmt::write!(fmt, "{}", "InvalidData"),
+            Self::SensorError(SensorError::Timeout) => defmt::write!(fmt, "{}", "Timeout"),
+            Self::SensorError(SensorError::PinError) => defmt::write!(fmt, "{}", "PinError"),
+            Self::SensorError(SensorError::BusError) => defmt::write!(fmt, "{}", "BusError"),
         }
     }
 }
 
-impl From<DHTSensorError> for FormattableDHTSensorError {
+impl From<SensorError> for FormattableSensorError {
+    fn from(err: SensorError) -> Self {
+        Self::SensorError(err)
+    }
+}
+
+// The DS18B20 driver reports the errors of the DHT drivers.
+impl From<DHTSensorError> for FormattableSensorError {
     fn from(err: DHTSensorError) -> Self {
-        Self::DHTSensorError(err)
+        Self::SensorError(err.into())
     }
 }
@@ -1,60 +1,389 @@
-use defmt::{info, warn};
-use embassy_dht_rp2350_sensor::DHTSensor;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use defmt::{debug, info, warn};
+use embassy_dht_rp2350_sensor::bme280::Bme280;
+use embassy_dht_rp2350_sensor::ds18b20::Ds18b20;
+use embassy_dht_rp2350_sensor::pms5003::Pms5003;
+use embassy_dht_rp2350_sensor::scd4x::Scd4x;
+use embassy_dht_rp2350_sensor::sht4x::Sht4x;
+use embassy_dht_rp2350_sensor::{DHTSensor, EnvironmentalSensor, OneWirePio};
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
 use embassy_executor::Spawner;
-use embassy_rp::{
-    peripherals::PIO0,
-    pio::{Common, Pin, StateMachine},
-};
-use embassy_time::Timer;
+use embassy_futures::select::{Either, select};
+use embassy_rp::i2c::{Async, I2c};
+use embassy_rp::peripherals::{I2C0, PIO0, PIO2};
+use embassy_rp::uart::BufferedUart;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Delay, Duration, Instant, Timer};
 
-use crate::temperature_and_humidity::error::FormattableDHTSensorError;
-use crate::{Measurement, TempHumidityChannel};
+use crate::clock::WallClock;
+use crate::config::calibration::{CalibrationConfig, ProvisionedCalibration, SENSOR_COUNT};
+use crate::config::sampling::{DEFAULT_SAMPLING_INTERVAL, clamp_interval};
+use crate::config::storage::{self, FlashPartition};
+use crate::device::DeviceId;
+use crate::filter::MeasurementFilter;
+use crate::publish::PublishPolicy;
+use crate::temperature_and_humidity::error::FormattableSensorError;
+use crate::{DeviceConfigSignal, Measurement, TempHumidityChannel};
 
 type Pio = PIO0;
-type DHTStateMachine = StateMachine<'static, Pio, 0>;
+type PioDHTSensor<const SM: usize> = DHTSensor<'static, Pio, SM>;
+// The I2C sensors have different addresses and share one bus.
+pub type I2cBus = Mutex<NoopRawMutex, I2c<'static, I2C0, Async>>;
+type SharedI2c = I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>;
+pub type I2cSht4xSensor = Sht4x<SharedI2c, Delay>;
+pub type I2cBme280Sensor = Bme280<SharedI2c, Delay>;
+pub type I2cScd4xSensor = Scd4x<SharedI2c, Delay>;
+pub type UartPms5003Sensor = Pms5003<BufferedUart>;
+// The 1-Wire program doesn't fit into PIO0 next to the DHT program.
+pub type PioDs18b20Probes = Ds18b20<OneWirePio<'static, PIO2, 0>, Delay>;
+
+type SampleFuture<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+// A sensor as seen by the sampling loop. Async trait methods can't be called through `dyn`,
+// the future is boxed instead so sensors of all drivers fit into one collection.
+pub trait SampledSensor {
+    fn min_request_interval(&self) -> Duration;
+
+    // Adds a measurement for every value read, errors are only logged.
+    fn sample<'a>(
+        &'a mut self,
+        sensor: u8,
+        measurements: &'a mut Vec<Measurement>,
+    ) -> SampleFuture<'a>;
+}
+
+impl<const SM: usize> SampledSensor for PioDHTSensor<SM> {
+    fn min_request_interval(&self) -> Duration {
+        EnvironmentalSensor::min_request_interval(self)
+    }
+
+    fn sample<'a>(
+        &'a mut self,
+        sensor: u8,
+        measurements: &'a mut Vec<Measurement>,
+    ) -> SampleFuture<'a> {
+        Box::pin(async move {
+            read_sensor(sensor, self, measurements).await;
+            log_signal_quality(sensor, self);
+        })
+    }
+}
+
+macro_rules! sampled_environmental_sensor {
+    ($($sensor:ty),*) => {
+        $(
+            impl SampledSensor for $sensor {
+                fn min_request_interval(&self) -> Duration {
+                    EnvironmentalSensor::min_request_interval(self)
+                }
+
+                fn sample<'a>(
+                    &'a mut self,
+                    sensor: u8,
+                    measurements: &'a mut Vec<Measurement>,
+                ) -> SampleFuture<'a> {
+                    Box::pin(read_sensor(sensor, self, measurements))
+                }
+            }
+        )*
+    };
+}
+
+sampled_environmental_sensor!(
+    I2cSht4xSensor,
+    I2cBme280Sensor,
+    I2cScd4xSensor,
+    UartPms5003Sensor
+);
+
+impl SampledSensor for PioDs18b20Probes {
+    // The conversion time is waited for while reading.
+    fn min_request_interval(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+
+    fn sample<'a>(
+        &'a mut self,
+        sensor: u8,
+        measurements: &'a mut Vec<Measurement>,
+    ) -> SampleFuture<'a> {
+        Box::pin(read_probes(sensor, self, measurements))
+    }
+}
+
+// The sensors of the hub, each with the index its measurements are sent with.
+// The index selects the calibration and the filter of the sensor, so it has to stay the same
+// across firmware updates. DS18B20 probes of a bus share one, their ROM tells them apart.
+#[derive(Default)]
+pub struct Sensors {
+    entries: Vec<(u8, Box<dyn SampledSensor>)>,
+}
+
+impl Sensors {
+    pub fn add(&mut self, sensor: u8, sampled_sensor: impl SampledSensor + 'static) {
+        assert!(
+            (sensor as usize) < SENSOR_COUNT,
+            "The sensor index has to be below {}",
+            SENSOR_COUNT
+        );
+        self.entries.push((sensor, Box::new(sampled_sensor)));
+    }
+
+    // The slowest sensor decides how often all of them can be read.
+    fn min_request_interval(&self) -> Duration {
+        self.entries
+            .iter()
+            .map(|(_, sampled_sensor)| sampled_sensor.min_request_interval())
+            .max()
+            .unwrap_or(Duration::from_secs(0))
+    }
+}
+
+// Everything a reading passes on its way to the channel.
+struct Processing {
+    calibration: CalibrationConfig,
+    filters: [MeasurementFilter; SENSOR_COUNT],
+    publish_policy: PublishPolicy,
+    wall_clock: &'static WallClock,
+    device: Option<DeviceId>,
+}
 
 pub async fn spawn_tasks(
     spawner: &Spawner,
-    sensor_pin: Pin<'static, Pio>,
-    common: Common<'static, Pio>,
-    state_machine: DHTStateMachine,
+    sensors: Sensors,
+    config_flash: FlashPartition,
     temp_humidity_channel: &'static TempHumidityChannel,
+    device_config_signal: &'static DeviceConfigSignal,
+    wall_clock: &'static WallClock,
+    device: Option<DeviceId>,
 ) {
-    spawner
-        .spawn(read_sensor_task(sensor_pin, common, state_machine, temp_humidity_channel).unwrap());
+    spawner.spawn(
+        read_sensor_task(
+            sensors,
+            config_flash,
+            temp_humidity_channel,
+            device_config_signal,
+            wall_clock,
+            device,
+        )
+        .unwrap(),
+    );
 }
 
 #[embassy_executor::task]
 async fn read_sensor_task(
-    sensor_pin: Pin<'static, Pio>,
-    common: Common<'static, Pio>,
-    state_machine: DHTStateMachine,
+    mut sensors: Sensors,
+    mut config_flash: FlashPartition,
     temp_humidity_channel: &'static TempHumidityChannel,
+    device_config_signal: &'static DeviceConfigSignal,
+    wall_clock: &'static WallClock,
+    device: Option<DeviceId>,
 ) {
-    let mut dht_sensor = DHTSensor::new(sensor_pin, common, state_machine);
-
+    let mut processing = Processing {
+        calibration: storage::load_calibration(&mut config_flash),
+        filters: Default::default(),
+        publish_policy: PublishPolicy::default(),
+        wall_clock,
+        device,
+    };
+    let min_request_interval = sensors.min_request_interval();
+    let mut sampling_interval = clamp_interval(DEFAULT_SAMPLING_INTERVAL, min_request_interval);
+    let mut measurements = Vec::new();
     loop {
-        let measurement = dht_sensor.read().await;
-        match measurement {
-            Ok(measurement) => {
-                info!(
-                    "Temperature: {}, Humidity: {}",
-                    measurement.temperature, measurement.humidity
-                );
-                temp_humidity_channel
-                    .send(Measurement {
-                        temperature: measurement.temperature,
-                        humidity: measurement.humidity,
-                    })
-                    .await;
+        let started = Instant::now();
+        for (sensor, sampled_sensor) in sensors.entries.iter_mut() {
+            sampled_sensor.sample(*sensor, &mut measurements).await;
+            for measurement in measurements.drain(..) {
+                send_measurement(measurement, &mut processing, temp_humidity_channel).await;
+            }
+        }
+        // A new interval applies to the running wait, counted from the start of the last read.
+        while let Either::Second(config) = select(
+            Timer::at(started + sampling_interval),
+            device_config_signal.wait(),
+        )
+        .await
+        {
+            if let Some(requested_interval) = config.sampling_interval() {
+                let interval = clamp_interval(requested_interval, min_request_interval);
+                if interval != sampling_interval {
+                    info!("Sampling interval changed to {} s", interval.as_secs());
+                    sampling_interval = interval;
+                }
+            }
+            if let Some(provisioned) = &config.calibration {
+                provision_calibration(provisioned, &mut processing.calibration, &mut config_flash);
+            }
+        }
+    }
+}
+
+// The config is polled every minute, the calibration is only stored when its version changed
+// to spare the flash.
+fn provision_calibration(
+    provisioned: &ProvisionedCalibration,
+    calibration: &mut CalibrationConfig,
+    config_flash: &mut FlashPartition,
+) {
+    if provisioned.version == calibration.version {
+        return;
+    }
+    match CalibrationConfig::try_from(provisioned) {
+        Ok(provisioned) => {
+            // The calibration applies until the next boot even if it can't be stored.
+            if let Err(err) = storage::store_calibration(config_flash, &provisioned) {
+                warn!("Storing the calibration failed with: {}", err);
             }
+            info!("Calibration version {} provisioned", provisioned.version);
+            *calibration = provisioned;
+        }
+        Err(err) => warn!(
+            "Ignoring calibration version {}: {}",
+            provisioned.version, err
+        ),
+    }
+}
+
+fn log_signal_quality<const SM: usize>(sensor: u8, dht_sensor: &PioDHTSensor<SM>) {
+    if let Some(signal_quality) = dht_sensor.signal_quality() {
+        debug!(
+            "Sensor {}: high phases between {} and {} us, {} us margin to the decision threshold",
+            sensor,
+            signal_quality.min_high_us,
+            signal_quality.max_high_us,
+            signal_quality.margin_us
+        );
+    }
+}
+
+async fn read_sensor(
+    sensor: u8,
+    env_sensor: &mut impl EnvironmentalSensor,
+    measurements: &mut Vec<Measurement>,
+) {
+    match env_sensor.read().await {
+        Ok(reading) if reading.is_cached => match reading.error {
+            Some(err) => warn!(
+                "Sensor {}: skipping cached reading ({} ms old) after sensor error: {}",
+                sensor,
+                reading.age.as_millis(),
+                FormattableSensorError::from(err)
+            ),
+            None => debug!(
+                "Sensor {}: skipping cached reading ({} ms old)",
+                sensor,
+                reading.age.as_millis()
+            ),
+        },
+        Ok(reading) => {
+            let values = reading.values;
+            measurements.push(Measurement {
+                sensor,
+                temperature: values.temperature,
+                humidity: values.humidity,
+                pressure: values.pressure,
+                co2_ppm: values.co2_ppm,
+                pm1_0: values.pm1_0,
+                pm2_5: values.pm2_5,
+                pm10: values.pm10,
+                ..Default::default()
+            });
+        }
+        Err(err) => warn!(
+            "Sensor {}: error reading from sensor: {}",
+            sensor,
+            FormattableSensorError::from(err)
+        ),
+    }
+}
+
+async fn read_probes(
+    sensor: u8,
+    ds18b20_probes: &mut PioDs18b20Probes,
+    measurements: &mut Vec<Measurement>,
+) {
+    // Probes are searched for once, until at least one is found.
+    if ds18b20_probes.probes().is_empty() {
+        match ds18b20_probes.discover().await {
+            Ok(probes) => info!("Found {} DS18B20 probes", probes.len()),
             Err(err) => {
                 warn!(
-                    "Error reading from DHT sensor: {}",
-                    FormattableDHTSensorError::from(err)
+                    "Error searching for DS18B20 probes: {}",
+                    FormattableSensorError::from(err)
                 );
+                return;
             }
         }
-        Timer::after_millis(10000).await;
+    }
+    if let Err(err) = ds18b20_probes.convert().await {
+        warn!(
+            "Error starting DS18B20 conversion: {}",
+            FormattableSensorError::from(err)
+        );
+        return;
+    }
+    for i in 0..ds18b20_probes.probes().len() {
+        let probe = ds18b20_probes.probes()[i];
+        match ds18b20_probes.read_temperature(probe).await {
+            Ok(temperature) => measurements.push(Measurement {
+                sensor,
+                probe: Some(probe),
+                temperature: Some(temperature),
+                ..Default::default()
+            }),
+            Err(err) => warn!(
+                "Probe {:x}: error reading from DS18B20 probe: {}",
+                probe,
+                FormattableSensorError::from(err)
+            ),
+        }
+    }
+}
+
+// Every measurement passes the filter and the calibration of its sensor on the way to the
+// channel, unchanged measurements are only sent as heartbeat.
+async fn send_measurement(
+    mut measurement: Measurement,
+    processing: &mut Processing,
+    temp_humidity_channel: &'static TempHumidityChannel,
+) {
+    info!("{}", measurement);
+    // Probes share a sensor index and are only calibrated, not filtered.
+    if let (None, Some(temperature), Some(humidity)) = (
+        measurement.probe,
+        measurement.temperature,
+        measurement.humidity,
+    ) {
+        let filter = &mut processing.filters[measurement.sensor as usize];
+        let Some((temperature, humidity)) = filter.push(temperature, humidity, Instant::now())
+        else {
+            warn!(
+                "Sensor {}: rejected outlier, {} samples rejected so far",
+                measurement.sensor,
+                filter.rejected()
+            );
+            return;
+        };
+        measurement.temperature = Some(temperature);
+        measurement.humidity = Some(humidity);
+        measurement.rejected = Some(filter.rejected());
+    }
+    measurement.timestamp = processing.wall_clock.now();
+    measurement.device = processing.device;
+    processing.calibration.apply(&mut measurement);
+    if processing
+        .publish_policy
+        .should_publish(&measurement, Instant::now())
+    {
+        temp_humidity_channel.send(measurement).await;
+    } else {
+        debug!(
+            "Sensor {}: measurement within deadband, not sent",
+            measurement.sensor
+        );
     }
 }
@@ -0,0 +1,96 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::{Duration, Instant};
+    use rp2350_sensor_hub::clock::WallClock;
+    use rp2350_sensor_hub::network::batch::Batch;
+    use rp2350_sensor_hub::Measurement;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn batch() -> Batch {
+        Batch::new(3, Duration::from_secs(60))
+    }
+
+    fn measurement(sensor: u8) -> Measurement {
+        Measurement {
+            sensor,
+            temperature: Some(21.0),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn empty_batch_has_no_deadline(batch: Batch) {
+        assert!(batch.is_empty());
+        assert_eq!(batch.deadline(), None);
+        assert!(!batch.is_ready(Instant::from_secs(3600)));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn ready_when_full(mut batch: Batch) {
+        for sensor in 0..3 {
+            assert!(!batch.is_ready(Instant::from_secs(sensor as u64)));
+            batch.push(measurement(sensor), Instant::from_secs(sensor as u64));
+        }
+
+        assert!(batch.is_ready(Instant::from_secs(3)));
+        assert_eq!(
+            batch
+                .measurements()
+                .iter()
+                .map(|measurement| measurement.sensor)
+                .collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn deadline_follows_the_oldest_measurement(mut batch: Batch) {
+        batch.push(measurement(0), Instant::from_secs(10));
+        batch.push(measurement(1), Instant::from_secs(40));
+
+        assert_eq!(batch.deadline(), Some(Instant::from_secs(70)));
+        assert!(!batch.is_ready(Instant::from_secs(69)));
+        assert!(batch.is_ready(Instant::from_secs(70)));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn clear_restarts_the_deadline(mut batch: Batch) {
+        batch.push(measurement(0), Instant::from_secs(10));
+        batch.clear();
+        batch.push(measurement(1), Instant::from_secs(100));
+
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.deadline(), Some(Instant::from_secs(160)));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn backfill_measurements_taken_before_the_clock_was_set(mut batch: Batch) {
+        let stamped = Measurement {
+            timestamp: Some(1_000),
+            ..measurement(0)
+        };
+        batch.push(stamped, Instant::from_secs(10));
+        batch.push(measurement(1), Instant::from_secs(20));
+        let wall_clock = WallClock::new();
+        wall_clock.set(1_760_000_030_000, Instant::from_secs(30));
+
+        batch.backfill(&wall_clock);
+
+        assert_eq!(
+            batch
+                .measurements()
+                .iter()
+                .map(|measurement| measurement.timestamp)
+                .collect::<Vec<_>>(),
+            [Some(1_000), Some(1_760_000_020_000)]
+        );
+    }
+}
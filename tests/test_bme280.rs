@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::bme280::{Bme280, Calibration, DEFAULT_ADDRESS};
+    use embassy_dht_rp2350_sensor::{DHTSensorError, EnvironmentalSensor, SensorValues};
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use rstest::rstest;
+
+    // The calibration example of the Bosch datasheets, with made up humidity coefficients.
+    const CALIBRATION_TP: [u8; 26] = [
+        0x70, 0x6B, 0x43, 0x67, 0x18, 0xFC, 0x7D, 0x8E, 0x43, 0xD6, 0xD0, 0x0B, 0x27, 0x0B, 0x8C,
+        0x00, 0xF9, 0xFF, 0x8C, 0x3C, 0xF8, 0xC6, 0x70, 0x17, 0x00, 0x4B,
+    ];
+    const CALIBRATION_H: [u8; 7] = [0x6A, 0x01, 0x00, 0x13, 0x29, 0x03, 0x1E];
+    // 25.08 degrees, 1006.53 hPa and 42.74% humidity with the calibration above.
+    const DATA: [u8; 8] = [0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, 0x6C, 0x98];
+
+    fn init_transactions() -> Vec<Transaction> {
+        vec![
+            Transaction::write_read(DEFAULT_ADDRESS, vec![0xD0], vec![0x60]),
+            Transaction::write(DEFAULT_ADDRESS, vec![0xE0, 0xB6]),
+            Transaction::write_read(DEFAULT_ADDRESS, vec![0x88], CALIBRATION_TP.to_vec()),
+            Transaction::write_read(DEFAULT_ADDRESS, vec![0xE1], CALIBRATION_H.to_vec()),
+        ]
+    }
+
+    fn measure_transactions() -> Vec<Transaction> {
+        vec![
+            Transaction::write(DEFAULT_ADDRESS, vec![0xF2, 0x01]),
+            Transaction::write(DEFAULT_ADDRESS, vec![0xF4, 0x25]),
+            Transaction::write_read(DEFAULT_ADDRESS, vec![0xF7], DATA.to_vec()),
+        ]
+    }
+
+    fn assert_response(response: SensorValues) {
+        assert!((response.temperature.unwrap() - 25.08).abs() < 0.001);
+        assert!((response.humidity.unwrap() - 42.74).abs() < 0.001);
+        assert!((response.pressure.unwrap() - 1006.53).abs() < 0.01);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn compensate() {
+        let calibration = Calibration::from_registers(&CALIBRATION_TP, &CALIBRATION_H);
+
+        assert_response(calibration.compensate(&DATA));
+    }
+
+    #[rstest]
+    #[case::humidity_above_range([0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, 0xFF, 0xFF], 100.0)]
+    #[case::humidity_below_range([0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, 0x00, 0x00], 0.0)]
+    #[test_log::test]
+    fn compensate_clips_humidity(#[case] data: [u8; 8], #[case] expected_humidity: f32) {
+        let calibration = Calibration::from_registers(&CALIBRATION_TP, &CALIBRATION_H);
+
+        assert_eq!(
+            calibration.compensate(&data).humidity,
+            Some(expected_humidity)
+        );
+    }
+
+    #[rstest]
+    #[case::maximum_coefficients([0x7F; 26], [0x7F; 7], [0xFF; 8])]
+    #[case::minimum_coefficients([0x80; 26], [0x80; 7], [0xFF; 8])]
+    #[case::zero_data([0x7F; 26], [0x80; 7], [0x00; 8])]
+    #[test_log::test]
+    fn compensate_does_not_overflow(
+        #[case] calibration_tp: [u8; 26],
+        #[case] calibration_h: [u8; 7],
+        #[case] data: [u8; 8],
+    ) {
+        let calibration = Calibration::from_registers(&calibration_tp, &calibration_h);
+
+        let response = calibration.compensate(&data);
+
+        assert!((0.0..=100.0).contains(&response.humidity.unwrap()));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn measure() {
+        let mut transactions = init_transactions();
+        transactions.extend(measure_transactions());
+        transactions.extend(measure_transactions());
+        let i2c = Mock::new(&transactions);
+        let mut sensor = Bme280::new(i2c, NoopDelay::new());
+
+        // The calibration is only read by the first measurement.
+        assert_response(sensor.measure().await.unwrap());
+        assert_response(sensor.measure().await.unwrap());
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read() {
+        let mut transactions = init_transactions();
+        transactions.extend(measure_transactions());
+        let i2c = Mock::new(&transactions);
+        let mut sensor = Bme280::new(i2c, NoopDelay::new());
+
+        let reading = EnvironmentalSensor::read(&mut sensor).await.unwrap();
+        assert!(!reading.is_cached);
+        assert!((reading.values.temperature.unwrap() - 25.08).abs() < 0.001);
+        assert!((reading.values.humidity.unwrap() - 42.74).abs() < 0.001);
+        assert!((reading.values.pressure.unwrap() - 1006.53).abs() < 0.01);
+        assert_eq!(reading.values.co2_ppm, None);
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn init_with_unknown_chip() {
+        // A BMP280 has no humidity sensor.
+        let i2c = Mock::new(&[Transaction::write_read(
+            DEFAULT_ADDRESS,
+            vec![0xD0],
+            vec![0x58],
+        )]);
+        let mut sensor = Bme280::new(i2c, NoopDelay::new());
+
+        assert_eq!(sensor.init().await, Err(DHTSensorError::InvalidData));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+}
@@ -0,0 +1,259 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use rp2350_sensor_hub::config::calibration::{
+        Calibration, CalibrationConfig, CalibrationEntry, ProbeCalibration, ProvisionedCalibration,
+        CALIBRATION_CONFIG_LEN, MAX_PROBE_CALIBRATIONS,
+    };
+    use rp2350_sensor_hub::config::error::ConfigError;
+    use rp2350_sensor_hub::Measurement;
+    use rstest::{fixture, rstest};
+
+    const DHT_SENSOR: u8 = 1;
+    const PROBE_BUS: u8 = 6;
+    const PROBE: u64 = 0x2800_0316_A279_1EFF;
+
+    #[fixture]
+    fn calibration() -> CalibrationConfig {
+        let mut calibration = CalibrationConfig {
+            version: 3,
+            ..Default::default()
+        };
+        calibration.sensors[DHT_SENSOR as usize] = Calibration {
+            temperature_offset: -0.8,
+            temperature_gain: 1.0,
+            humidity_offset: 2.0,
+            humidity_gain: 1.1,
+        };
+        calibration.probes.push(ProbeCalibration {
+            sensor: PROBE_BUS,
+            probe: PROBE,
+            calibration: Calibration {
+                temperature_offset: 0.3,
+                ..Default::default()
+            },
+        });
+        calibration
+    }
+
+    fn probe_measurement(probe: u64) -> Measurement {
+        Measurement {
+            sensor: PROBE_BUS,
+            probe: Some(probe),
+            temperature: Some(4.0),
+            ..Default::default()
+        }
+    }
+
+    fn measurement(sensor: u8) -> Measurement {
+        Measurement {
+            sensor,
+            temperature: Some(22.3),
+            humidity: Some(40.0),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn apply_calibration_of_sensor(calibration: CalibrationConfig) {
+        let mut measurement = measurement(DHT_SENSOR);
+
+        calibration.apply(&mut measurement);
+
+        assert_eq!(measurement.temperature, Some(22.3 - 0.8));
+        assert_eq!(measurement.humidity, Some(40.0 * 1.1 + 2.0));
+        assert_eq!(measurement.calibration, Some(3));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn identity_calibration_of_other_sensor(calibration: CalibrationConfig) {
+        let mut measurement = measurement(0);
+
+        calibration.apply(&mut measurement);
+
+        assert_eq!(measurement.temperature, Some(22.3));
+        assert_eq!(measurement.humidity, Some(40.0));
+        assert_eq!(measurement.calibration, Some(3));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn apply_calibration_of_probe(calibration: CalibrationConfig) {
+        let mut measurement = probe_measurement(PROBE);
+
+        calibration.apply(&mut measurement);
+
+        assert_eq!(measurement.temperature, Some(4.0 + 0.3));
+        assert_eq!(measurement.calibration, Some(3));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn identity_calibration_of_other_probe_on_bus(calibration: CalibrationConfig) {
+        let mut measurement = probe_measurement(0x2800_0000_0000_0001);
+
+        calibration.apply(&mut measurement);
+
+        assert_eq!(measurement.temperature, Some(4.0));
+        assert_eq!(measurement.calibration, Some(3));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn uncalibrated_measurement_is_raw() {
+        let mut measurement = measurement(DHT_SENSOR);
+
+        CalibrationConfig::default().apply(&mut measurement);
+
+        assert_eq!(measurement.temperature, Some(22.3));
+        assert_eq!(measurement.calibration, None);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn particulate_matter_is_not_calibrated(calibration: CalibrationConfig) {
+        let mut measurement = Measurement {
+            sensor: 8,
+            pm2_5: Some(12),
+            ..Default::default()
+        };
+
+        calibration.apply(&mut measurement);
+
+        assert_eq!(measurement.pm2_5, Some(12));
+        assert_eq!(measurement.calibration, None);
+    }
+
+    #[rstest]
+    #[case::below_zero(-5.0, 0.0)]
+    #[case::above_saturation(99.0, 100.0)]
+    #[test_log::test]
+    fn calibrated_humidity_is_clamped(#[case] humidity: f32, #[case] expected: f32) {
+        let calibration = Calibration {
+            humidity_offset: 2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(calibration.humidity(humidity), expected);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn round_trip(calibration: CalibrationConfig) {
+        assert_eq!(
+            CalibrationConfig::from_bytes(&calibration.to_bytes()),
+            Ok(calibration)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn round_trip_with_all_probes(mut calibration: CalibrationConfig) {
+        calibration.probes = (0..MAX_PROBE_CALIBRATIONS as u64)
+            .map(|probe| ProbeCalibration {
+                sensor: PROBE_BUS,
+                probe,
+                calibration: Calibration {
+                    temperature_offset: probe as f32 / 10.0,
+                    ..Default::default()
+                },
+            })
+            .collect();
+
+        assert_eq!(
+            CalibrationConfig::from_bytes(&calibration.to_bytes()),
+            Ok(calibration)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn from_provisioned(calibration: CalibrationConfig) {
+        let mut sensors = calibration
+            .probes
+            .iter()
+            .map(|entry| CalibrationEntry {
+                sensor: entry.sensor,
+                probe: Some(entry.probe),
+                calibration: entry.calibration,
+            })
+            .collect::<Vec<_>>();
+        sensors.push(CalibrationEntry {
+            sensor: DHT_SENSOR,
+            probe: None,
+            calibration: calibration.sensors[DHT_SENSOR as usize],
+        });
+        let provisioned = ProvisionedCalibration {
+            version: 3,
+            sensors: sensors.into_iter().collect(),
+        };
+
+        assert_eq!(CalibrationConfig::try_from(&provisioned), Ok(calibration));
+    }
+
+    #[rstest]
+    #[case::unknown_sensor(9, None, 0.0)]
+    #[case::not_finite(DHT_SENSOR, None, f32::NAN)]
+    #[case::not_finite_probe(PROBE_BUS, Some(PROBE), f32::INFINITY)]
+    #[test_log::test]
+    fn invalid_provisioned(
+        #[case] sensor: u8,
+        #[case] probe: Option<u64>,
+        #[case] temperature_offset: f32,
+    ) {
+        let provisioned = ProvisionedCalibration {
+            version: 3,
+            sensors: [CalibrationEntry {
+                sensor,
+                probe,
+                calibration: Calibration {
+                    temperature_offset,
+                    ..Default::default()
+                },
+            }]
+            .into_iter()
+            .collect(),
+        };
+
+        assert_eq!(
+            CalibrationConfig::try_from(&provisioned),
+            Err(ConfigError::InvalidData)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn erased_flash() {
+        assert_eq!(
+            CalibrationConfig::from_bytes(&[0xFF; CALIBRATION_CONFIG_LEN]),
+            Err(ConfigError::Erased)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn corrupted_config(calibration: CalibrationConfig) {
+        let mut bytes = calibration.to_bytes();
+        bytes[10] ^= 0x01;
+
+        assert_eq!(
+            CalibrationConfig::from_bytes(&bytes),
+            Err(ConfigError::ChecksumError)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn unknown_config(calibration: CalibrationConfig) {
+        let mut bytes = calibration.to_bytes();
+        bytes[0] = b'X';
+
+        assert_eq!(
+            CalibrationConfig::from_bytes(&bytes),
+            Err(ConfigError::InvalidData)
+        );
+    }
+}
@@ -0,0 +1,41 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use rp2350_sensor_hub::device::DeviceId;
+    use rp2350_sensor_hub::Measurement;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::chip_id(0xE661_4C31_1B2F_6A2D, "e6614c311b2f6a2d")]
+    #[case::leading_zeros(0x0000_0000_0000_00AB, "00000000000000ab")]
+    #[case::zero(0, "0000000000000000")]
+    #[case::max(u64::MAX, "ffffffffffffffff")]
+    #[test_log::test]
+    fn to_hex(#[case] chip_id: u64, #[case] expected: &str) {
+        let device = DeviceId::new(chip_id);
+
+        assert_eq!(&device.to_hex(), expected.as_bytes());
+        assert_eq!(device.value(), chip_id);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn serialize_probe_as_hex() {
+        let measurement = Measurement {
+            sensor: 6,
+            probe: Some(0x2800_0316_A279_1EFF),
+            temperature: Some(4.5),
+            device: Some(DeviceId::new(0xAB)),
+            ..Default::default()
+        };
+        let mut buffer = [0; 256];
+
+        let length = serde_json_core::to_slice(&measurement, &mut buffer).unwrap();
+
+        assert_eq!(
+            core::str::from_utf8(&buffer[..length]).unwrap(),
+            r#"{"sensor":6,"probe":"28000316a2791eff","temperature":4.5,"device":"00000000000000ab"}"#
+        );
+    }
+}
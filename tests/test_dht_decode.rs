@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::decode;
+    use embassy_dht_rp2350_sensor::{DHTSensorError, DTHResponse, SensorModel};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::positive_temperature(SensorModel::Dht2x, [0x02, 0x8C, 0x01, 0x5F, 0xEE], 65.2, 35.1)]
+    #[case::negative_temperature(SensorModel::Dht2x, [0x02, 0x8C, 0x80, 0x65, 0x73], 65.2, -10.1)]
+    #[case::checksum_overflow(SensorModel::Dht2x, [0x03, 0xE7, 0x00, 0xFF, 0xE9], 99.9, 25.5)]
+    #[case::zero(SensorModel::Dht2x, [0x00, 0x00, 0x00, 0x00, 0x00], 0.0, 0.0)]
+    #[case::max_humidity(SensorModel::Dht2x, [0x03, 0xE8, 0x00, 0xC8, 0xB3], 100.0, 20.0)]
+    #[case::dht1x_positive_temperature(SensorModel::Dht1x, [0x2A, 0x00, 0x17, 0x00, 0x41], 42.0, 23.0)]
+    #[case::dht1x_negative_temperature(SensorModel::Dht1x, [0x2A, 0x00, 0x85, 0x05, 0xB4], 42.0, -5.5)]
+    #[test_log::test]
+    fn decode_valid_frame(
+        #[case] model: SensorModel,
+        #[case] bytes: [u8; 5],
+        #[case] humidity: f32,
+        #[case] temperature: f32,
+    ) {
+        let frame = decode::frame_from_bytes(&bytes);
+
+        assert_eq!(
+            decode::decode(frame, model),
+            Ok(DTHResponse {
+                humidity,
+                temperature
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::wrong_checksum(SensorModel::Dht2x, [0x02, 0x8C, 0x01, 0x5F, 0xEF], DHTSensorError::ChecksumError)]
+    #[case::checksum_without_overflow(SensorModel::Dht2x, [0x03, 0xE7, 0x00, 0xFF, 0x01], DHTSensorError::ChecksumError)]
+    #[case::humidity_out_of_range(SensorModel::Dht2x, [0x03, 0xE9, 0x00, 0xC8, 0xB4], DHTSensorError::InvalidData)]
+    #[case::humidity_all_ones(SensorModel::Dht2x, [0xFF, 0xFF, 0x00, 0xC8, 0xC6], DHTSensorError::InvalidData)]
+    #[case::dht1x_humidity_out_of_range(SensorModel::Dht1x, [0x65, 0x00, 0x17, 0x00, 0x7C], DHTSensorError::InvalidData)]
+    #[test_log::test]
+    fn decode_invalid_frame(
+        #[case] model: SensorModel,
+        #[case] bytes: [u8; 5],
+        #[case] error: DHTSensorError,
+    ) {
+        let frame = decode::frame_from_bytes(&bytes);
+
+        assert_eq!(decode::decode(frame, model), Err(error));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn frame_from_pio_words() {
+        let frame = decode::frame(0x028C_015F, 0xEE);
+
+        assert_eq!(
+            frame,
+            decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE])
+        );
+        assert_eq!(decode::checksum(0x028C_015F), 0xEE);
+    }
+
+    #[rstest]
+    #[case::dht1x(SensorModel::Dht1x, 1, 18_000)]
+    #[case::dht2x(SensorModel::Dht2x, 2, 1_100)]
+    #[test_log::test]
+    fn sensor_model_timings(
+        #[case] model: SensorModel,
+        #[case] min_request_interval_secs: u64,
+        #[case] start_low_interval_us: u64,
+    ) {
+        assert_eq!(model.min_request_interval_secs(), min_request_interval_secs);
+        assert_eq!(model.start_low_interval_us(), start_low_interval_us);
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::decode::{self, FRAME_BITS};
+    use embassy_dht_rp2350_sensor::diagnostics::{self, Pulse, SignalQuality};
+    use embassy_dht_rp2350_sensor::{DHTSensorError, DTHResponse, SensorModel};
+    use rstest::rstest;
+
+    const ZERO_HIGH_US: u16 = 27;
+    const ONE_HIGH_US: u16 = 70;
+
+    // Encode a frame as it would be measured on a clean line.
+    fn pulses(frame: u64) -> [Pulse; FRAME_BITS] {
+        let mut pulses = [Pulse::default(); FRAME_BITS];
+        for (i, pulse) in pulses.iter_mut().enumerate() {
+            let bit = (frame >> (FRAME_BITS - 1 - i)) & 1 == 1;
+            *pulse = Pulse {
+                low_us: 50,
+                high_us: if bit { ONE_HIGH_US } else { ZERO_HIGH_US },
+            };
+        }
+        pulses
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn pulse_from_pio_word() {
+        // 50us low and 70us high, both counted down from 0xFFFF
+        let word = ((0xFFFF - 50) << 16) | (0xFFFF - 70);
+
+        assert_eq!(
+            Pulse::from_pio_word(word),
+            Pulse {
+                low_us: 50,
+                high_us: 70
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::positive_temperature([0x02, 0x8C, 0x01, 0x5F, 0xEE])]
+    #[case::negative_temperature([0x02, 0x8C, 0x80, 0x65, 0x73])]
+    #[test_log::test]
+    fn frame_from_pulses(#[case] bytes: [u8; 5]) {
+        let frame = decode::frame_from_bytes(&bytes);
+
+        assert_eq!(diagnostics::frame(&pulses(frame)), frame);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn decode_frame_from_pulses() {
+        let frame = decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE]);
+
+        assert_eq!(
+            decode::decode(diagnostics::frame(&pulses(frame)), SensorModel::Dht2x),
+            Ok(DTHResponse {
+                humidity: 65.2,
+                temperature: 35.1
+            })
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn signal_quality_of_clean_line() {
+        let frame = decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE]);
+
+        assert_eq!(
+            diagnostics::signal_quality(&pulses(frame)),
+            SignalQuality {
+                min_high_us: ZERO_HIGH_US,
+                max_high_us: ONE_HIGH_US,
+                margin_us: diagnostics::DECISION_THRESHOLD_US - ZERO_HIGH_US,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::stretched_zero(40, ONE_HIGH_US, 3)]
+    #[case::shortened_one(ZERO_HIGH_US, 45, 2)]
+    #[test_log::test]
+    fn signal_quality_close_to_threshold(
+        #[case] zero_high_us: u16,
+        #[case] one_high_us: u16,
+        #[case] margin_us: u16,
+    ) {
+        let mut pulses = pulses(decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE]));
+        // the first humidity byte 0x02 has a "0" as first and a "1" as seventh bit
+        pulses[0].high_us = zero_high_us;
+        pulses[6].high_us = one_high_us;
+
+        let signal_quality = diagnostics::signal_quality(&pulses);
+
+        assert_eq!(signal_quality.margin_us, margin_us);
+        assert_eq!(
+            diagnostics::frame(&pulses),
+            decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE])
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn checked_frame_of_clean_line() {
+        let frame = decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE]);
+
+        assert_eq!(diagnostics::checked_frame(&pulses(frame)), Ok(frame));
+    }
+
+    #[rstest]
+    // a missed rising edge merges a low, a high and the next low phase
+    #[case::merged_phases(0, 50, 127)]
+    #[case::short_low(0, 20, ZERO_HIGH_US)]
+    #[case::long_low(0, 95, ZERO_HIGH_US)]
+    #[case::glitch(0, 50, 5)]
+    #[case::long_high(6, 50, 110)]
+    #[test_log::test]
+    fn checked_frame_rejects_out_of_bounds_phases(
+        #[case] bit: usize,
+        #[case] low_us: u16,
+        #[case] high_us: u16,
+    ) {
+        let mut pulses = pulses(decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEE]));
+        pulses[bit] = Pulse { low_us, high_us };
+
+        assert_eq!(
+            diagnostics::checked_frame(&pulses),
+            Err(DHTSensorError::InvalidData)
+        );
+    }
+}
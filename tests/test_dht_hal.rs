@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::decode;
+    use embassy_dht_rp2350_sensor::dht_hal::DHTSensor;
+    use embassy_dht_rp2350_sensor::{DHTSensorError, DTHResponse, SensorModel};
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::digital::{Mock, State, Transaction};
+    use rstest::rstest;
+
+    // Start request by the host followed by the ~80us low and high acknowledge of the sensor.
+    fn start_transactions() -> Vec<Transaction> {
+        vec![
+            Transaction::set(State::Low),
+            Transaction::set(State::High),
+            Transaction::get(State::Low),
+            Transaction::get(State::High),
+            Transaction::get(State::Low),
+        ]
+    }
+
+    // The line as it is seen by the driver: the rising edge of every bit, the level at the
+    // decision threshold and, for a "1", the falling edge at the end of the bit.
+    fn frame_transactions(frame: u64) -> Vec<Transaction> {
+        let mut transactions = start_transactions();
+        for i in (0..decode::FRAME_BITS).rev() {
+            transactions.push(Transaction::get(State::High));
+            if (frame >> i) & 1 == 1 {
+                transactions.push(Transaction::get(State::High));
+                transactions.push(Transaction::get(State::Low));
+            } else {
+                transactions.push(Transaction::get(State::Low));
+            }
+        }
+        transactions
+    }
+
+    #[rstest]
+    #[case::positive_temperature(
+        [0x02, 0x8C, 0x01, 0x5F, 0xEE],
+        DTHResponse { humidity: 65.2, temperature: 35.1 }
+    )]
+    #[case::negative_temperature(
+        [0x02, 0x8C, 0x80, 0x65, 0x73],
+        DTHResponse { humidity: 65.2, temperature: -10.1 }
+    )]
+    #[test_log::test]
+    fn read(#[case] bytes: [u8; 5], #[case] expected: DTHResponse) {
+        let pin = Mock::new(&frame_transactions(decode::frame_from_bytes(&bytes)));
+        let mut sensor = DHTSensor::new(pin, NoopDelay::new(), SensorModel::Dht2x);
+
+        assert_eq!(sensor.read(), Ok(expected));
+
+        let (mut pin, _) = sensor.release();
+        pin.done();
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn read_with_invalid_checksum() {
+        let frame = decode::frame_from_bytes(&[0x02, 0x8C, 0x01, 0x5F, 0xEF]);
+        let pin = Mock::new(&frame_transactions(frame));
+        let mut sensor = DHTSensor::new(pin, NoopDelay::new(), SensorModel::Dht2x);
+
+        assert_eq!(sensor.read(), Err(DHTSensorError::ChecksumError));
+
+        let (mut pin, _) = sensor.release();
+        pin.done();
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn read_without_acknowledge() {
+        // The sensor never pulls the line low after the start request.
+        let mut transactions = start_transactions()[..2].to_vec();
+        transactions.extend(std::iter::repeat_n(Transaction::get(State::High), 100));
+        let pin = Mock::new(&transactions);
+        let mut sensor = DHTSensor::new(pin, NoopDelay::new(), SensorModel::Dht2x);
+
+        assert_eq!(sensor.read(), Err(DHTSensorError::Timeout));
+
+        let (mut pin, _) = sensor.release();
+        pin.done();
+    }
+}
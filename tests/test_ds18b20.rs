@@ -0,0 +1,288 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::ds18b20::{self, Ds18b20};
+    use embassy_dht_rp2350_sensor::onewire::{self, OneWireBus, RomSearch};
+    use embassy_dht_rp2350_sensor::DHTSensorError;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use rstest::rstest;
+
+    enum Phase {
+        Command(Vec<bool>),
+        Search { bit: usize, step: usize },
+        MatchRom(Vec<bool>),
+        Function(Vec<bool>),
+        ReadScratchpad(usize),
+        Idle,
+    }
+
+    struct Device {
+        rom: u64,
+        scratchpad: [u8; 9],
+    }
+
+    // Devices on an open drain bus: the line is low as soon as one of them pulls it low.
+    struct SimulatedBus {
+        devices: Vec<Device>,
+        selected: Vec<bool>,
+        phase: Phase,
+        conversions: usize,
+    }
+
+    impl SimulatedBus {
+        fn new(devices: Vec<Device>) -> Self {
+            SimulatedBus {
+                selected: vec![false; devices.len()],
+                devices,
+                phase: Phase::Idle,
+                conversions: 0,
+            }
+        }
+
+        fn line(&self, bit: impl Fn(&Device) -> bool) -> bool {
+            self.devices
+                .iter()
+                .zip(&self.selected)
+                .filter(|(_, selected)| **selected)
+                .all(|(device, _)| bit(device))
+        }
+
+        fn collect_byte(bits: &mut Vec<bool>, bit: bool) -> Option<u8> {
+            bits.push(bit);
+            (bits.len() == 8).then(|| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, bit)| byte | ((*bit as u8) << i))
+            })
+        }
+    }
+
+    impl OneWireBus for SimulatedBus {
+        async fn reset(&mut self) -> bool {
+            self.selected = vec![true; self.devices.len()];
+            self.phase = Phase::Command(Vec::new());
+            !self.devices.is_empty()
+        }
+
+        async fn touch_bit(&mut self, bit: bool) -> bool {
+            let phase = std::mem::replace(&mut self.phase, Phase::Idle);
+            let (phase, line) = match phase {
+                Phase::Command(mut bits) => match Self::collect_byte(&mut bits, bit) {
+                    Some(onewire::SEARCH_ROM) => (Phase::Search { bit: 0, step: 0 }, bit),
+                    Some(onewire::MATCH_ROM) => (Phase::MatchRom(Vec::new()), bit),
+                    Some(onewire::SKIP_ROM) => (Phase::Function(Vec::new()), bit),
+                    Some(command) => panic!("unexpected ROM command {command:#x}"),
+                    None => (Phase::Command(bits), bit),
+                },
+                Phase::Search { bit: n, step: 0 } => (
+                    Phase::Search { bit: n, step: 1 },
+                    self.line(|device| (device.rom >> n) & 1 == 1),
+                ),
+                Phase::Search { bit: n, step: 1 } => (
+                    Phase::Search { bit: n, step: 2 },
+                    self.line(|device| (device.rom >> n) & 1 == 0),
+                ),
+                Phase::Search { bit: n, .. } => {
+                    for (device, selected) in self.devices.iter().zip(self.selected.iter_mut()) {
+                        *selected &= ((device.rom >> n) & 1 == 1) == bit;
+                    }
+                    let phase = if n == 63 {
+                        Phase::Idle
+                    } else {
+                        Phase::Search {
+                            bit: n + 1,
+                            step: 0,
+                        }
+                    };
+                    (phase, bit)
+                }
+                Phase::MatchRom(mut bits) => {
+                    bits.push(bit);
+                    if bits.len() < 64 {
+                        (Phase::MatchRom(bits), bit)
+                    } else {
+                        let rom = bits
+                            .iter()
+                            .enumerate()
+                            .fold(0u64, |rom, (i, bit)| rom | ((*bit as u64) << i));
+                        for (device, selected) in self.devices.iter().zip(self.selected.iter_mut())
+                        {
+                            *selected &= device.rom == rom;
+                        }
+                        (Phase::Function(Vec::new()), bit)
+                    }
+                }
+                Phase::Function(mut bits) => match Self::collect_byte(&mut bits, bit) {
+                    Some(0x44) => {
+                        self.conversions += 1;
+                        (Phase::Idle, bit)
+                    }
+                    Some(0xBE) => (Phase::ReadScratchpad(0), bit),
+                    Some(command) => panic!("unexpected function command {command:#x}"),
+                    None => (Phase::Function(bits), bit),
+                },
+                Phase::ReadScratchpad(n) => (
+                    Phase::ReadScratchpad(n + 1),
+                    self.line(|device| (device.scratchpad[n / 8] >> (n % 8)) & 1 == 1),
+                ),
+                Phase::Idle => (Phase::Idle, bit),
+            };
+            self.phase = phase;
+            line
+        }
+    }
+
+    fn rom(family_code: u8, serial_number: u64) -> u64 {
+        let rom = (serial_number << 8) | family_code as u64;
+        let crc = onewire::crc8(&rom.to_le_bytes()[..7]);
+        rom | ((crc as u64) << 56)
+    }
+
+    fn scratchpad(raw_temperature: i16) -> [u8; 9] {
+        let [low, high] = raw_temperature.to_le_bytes();
+        let mut scratchpad = [low, high, 0x4B, 0x46, 0x7F, 0xFF, 0x0C, 0x10, 0x00];
+        scratchpad[8] = onewire::crc8(&scratchpad[..8]);
+        scratchpad
+    }
+
+    fn probe(serial_number: u64, raw_temperature: i16) -> Device {
+        Device {
+            rom: rom(ds18b20::FAMILY_CODE, serial_number),
+            scratchpad: scratchpad(raw_temperature),
+        }
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn crc8() {
+        // Example from Maxim application note 27
+        let rom = [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2];
+
+        assert_eq!(onewire::crc8(&rom[..7]), 0xA2);
+        assert_eq!(onewire::crc8(&rom), 0);
+    }
+
+    #[rstest]
+    #[case::positive(0x0191, 25.0625)]
+    #[case::negative(-0x00A2, -10.125)]
+    #[case::minimum(-0x0370, -55.0)]
+    #[case::zero(0x0000, 0.0)]
+    #[test_log::test]
+    fn temperature(#[case] raw_temperature: i16, #[case] expected: f32) {
+        assert_eq!(
+            ds18b20::temperature(&scratchpad(raw_temperature)),
+            Ok(expected)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn temperature_with_invalid_crc() {
+        let mut scratchpad = scratchpad(0x0191);
+        scratchpad[0] ^= 0x01;
+
+        assert_eq!(
+            ds18b20::temperature(&scratchpad),
+            Err(DHTSensorError::ChecksumError)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn temperature_of_a_bus_held_low() {
+        assert_eq!(ds18b20::temperature(&[0; 9]), Err(DHTSensorError::BusError));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn temperature_of_a_probe_that_lost_power() {
+        assert_eq!(
+            ds18b20::temperature(&scratchpad(0x0550)),
+            Err(DHTSensorError::NoData)
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn search() {
+        let roms = vec![
+            rom(0x28, 0x0000_0000_0001),
+            rom(0x28, 0x0316_A279_1EFF),
+            rom(0x10, 0x0000_0000_0001),
+            rom(0x28, 0x0316_A279_1EFE),
+        ];
+        let mut bus = SimulatedBus::new(
+            roms.iter()
+                .map(|rom| Device {
+                    rom: *rom,
+                    scratchpad: scratchpad(0),
+                })
+                .collect(),
+        );
+
+        let mut search = RomSearch::new();
+        let mut found = Vec::new();
+        while let Some(rom) = search.next(&mut bus).await.unwrap() {
+            found.push(rom);
+        }
+
+        found.sort();
+        let mut expected = roms.clone();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn search_empty_bus() {
+        let mut bus = SimulatedBus::new(Vec::new());
+
+        assert_eq!(RomSearch::new().next(&mut bus).await, Ok(None));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_probes() {
+        let bus = SimulatedBus::new(vec![
+            probe(0x0316_A279_1EFF, 0x0191),
+            Device {
+                rom: rom(0x10, 0x0000_0000_0001),
+                scratchpad: scratchpad(0),
+            },
+            probe(0x0000_0000_0001, -0x00A2),
+        ]);
+        let mut sensor = Ds18b20::new(bus, NoopDelay::new());
+
+        let probes = sensor.discover().await.unwrap().to_vec();
+        assert_eq!(probes.len(), 2);
+        sensor.convert().await.unwrap();
+
+        let mut temperatures = Vec::new();
+        for rom in probes {
+            temperatures.push((rom, sensor.read_temperature(rom).await.unwrap()));
+        }
+        temperatures.sort_by_key(|(rom, _)| *rom);
+        assert_eq!(
+            temperatures,
+            vec![
+                (rom(0x28, 0x0000_0000_0001), -10.125),
+                (rom(0x28, 0x0316_A279_1EFF), 25.0625),
+            ]
+        );
+
+        let (bus, _) = sensor.release();
+        assert_eq!(bus.conversions, 1);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_without_probes() {
+        let mut sensor = Ds18b20::new(SimulatedBus::new(Vec::new()), NoopDelay::new());
+
+        assert_eq!(sensor.discover().await, Ok(&[][..]));
+        assert_eq!(sensor.convert().await, Err(DHTSensorError::NoData));
+    }
+}
@@ -0,0 +1,156 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::Instant;
+    use rp2350_sensor_hub::filter::{Filter, FilterConfig, MeasurementFilter};
+    use rstest::rstest;
+
+    const PASS_THROUGH: FilterConfig = FilterConfig {
+        median_window: 1,
+        ema_alpha: 1.0,
+        max_rate_per_sec: None,
+    };
+
+    // One sample every 10 seconds, as read by `read_sensor_task`.
+    fn at(sample: u64) -> Instant {
+        Instant::from_secs(sample * 10)
+    }
+
+    fn push_all(filter: &mut Filter, samples: &[f32]) -> Vec<Option<f32>> {
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| filter.push(*sample, at(i as u64)))
+            .collect()
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn pass_through() {
+        let mut filter = Filter::new(PASS_THROUGH);
+
+        assert_eq!(
+            push_all(&mut filter, &[21.0, 35.0, 22.0]),
+            [Some(21.0), Some(35.0), Some(22.0)]
+        );
+    }
+
+    #[rstest]
+    #[case::single_spike(&[21.0, 21.2, 80.0, 21.4, 21.6], &[21.0, 21.1, 21.2, 21.4, 21.6])]
+    #[case::filling_window(&[20.0, 22.0], &[20.0, 21.0])]
+    #[test_log::test]
+    fn median(#[case] samples: &[f32], #[case] expected: &[f32]) {
+        let mut filter = Filter::new(FilterConfig {
+            median_window: 3,
+            ..PASS_THROUGH
+        });
+
+        let filtered: Vec<f32> = push_all(&mut filter, samples)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect();
+
+        assert_eq!(filtered, expected);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn exponential_moving_average() {
+        let mut filter = Filter::new(FilterConfig {
+            ema_alpha: 0.25,
+            ..PASS_THROUGH
+        });
+
+        assert_eq!(
+            push_all(&mut filter, &[20.0, 24.0, 24.0]),
+            [Some(20.0), Some(21.0), Some(21.75)]
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn reject_rate_of_change_outlier() {
+        let mut filter = Filter::new(FilterConfig {
+            max_rate_per_sec: Some(0.5),
+            ..PASS_THROUGH
+        });
+
+        assert_eq!(
+            push_all(&mut filter, &[21.0, 21.5, 60.0, 22.0]),
+            [Some(21.0), Some(21.5), None, Some(22.0)]
+        );
+        assert_eq!(filter.rejected(), 1);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn rate_is_relative_to_elapsed_time() {
+        let mut filter = Filter::new(FilterConfig {
+            max_rate_per_sec: Some(0.5),
+            ..PASS_THROUGH
+        });
+
+        assert_eq!(filter.push(21.0, Instant::from_secs(0)), Some(21.0));
+        assert_eq!(filter.push(30.0, Instant::from_secs(1)), None);
+        assert_eq!(filter.push(30.0, Instant::from_secs(60)), Some(30.0));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn accept_persistent_change() {
+        let mut filter = Filter::new(FilterConfig {
+            median_window: 3,
+            max_rate_per_sec: Some(0.5),
+            ..PASS_THROUGH
+        });
+
+        assert_eq!(
+            push_all(&mut filter, &[21.0, 40.0, 40.0, 40.0, 40.0]),
+            [Some(21.0), None, None, Some(40.0), Some(40.0)]
+        );
+        assert_eq!(filter.rejected(), 3);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn reject_measurement_if_either_value_is_an_outlier() {
+        let mut filter = MeasurementFilter::new(
+            FilterConfig {
+                max_rate_per_sec: Some(0.5),
+                ..PASS_THROUGH
+            },
+            FilterConfig {
+                max_rate_per_sec: Some(2.0),
+                ..PASS_THROUGH
+            },
+        );
+
+        assert_eq!(filter.push(21.0, 40.0, at(0)), Some((21.0, 40.0)));
+        assert_eq!(filter.push(21.0, 99.0, at(1)), None);
+        assert_eq!(filter.push(21.5, 41.0, at(2)), Some((21.5, 41.0)));
+        assert_eq!(filter.rejected(), 1);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn rejected_measurement_leaves_both_filters_unchanged() {
+        let mut filter = MeasurementFilter::new(
+            FilterConfig {
+                ema_alpha: 0.5,
+                max_rate_per_sec: Some(0.5),
+                ..PASS_THROUGH
+            },
+            FilterConfig {
+                ema_alpha: 0.5,
+                max_rate_per_sec: Some(2.0),
+                ..PASS_THROUGH
+            },
+        );
+
+        assert_eq!(filter.push(20.0, 40.0, at(0)), Some((20.0, 40.0)));
+        // The temperature is fine, but it must not enter its average with the humidity outlier.
+        assert_eq!(filter.push(21.0, 99.0, at(1)), None);
+        assert_eq!(filter.push(20.0, 40.0, at(2)), Some((20.0, 40.0)));
+    }
+}
@@ -0,0 +1,52 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::Duration;
+    use rp2350_sensor_hub::network::link::{Backoff, LinkDiagnostics, LinkLoss};
+    use rstest::rstest;
+
+    #[rstest]
+    #[test_log::test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10));
+
+        let delays: Vec<u64> = (0..6).map(|_| backoff.next_delay().as_secs()).collect();
+
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn backoff_starts_over_after_reset() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10));
+        for _ in 0..3 {
+            backoff.next_delay();
+        }
+        backoff.reset();
+
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn diagnostics_count_losses_and_reconnects() {
+        let mut diagnostics = LinkDiagnostics::default();
+        diagnostics.lost(LinkLoss::Link);
+        diagnostics.failed_join();
+        diagnostics.failed_join();
+        diagnostics.reconnected();
+        diagnostics.lost(LinkLoss::Lease);
+        diagnostics.reconnected();
+
+        assert_eq!(
+            diagnostics,
+            LinkDiagnostics {
+                reconnects: 2,
+                link_losses: 1,
+                lease_losses: 1,
+                failed_joins: 2,
+            }
+        );
+    }
+}
@@ -2,30 +2,41 @@ include!("common/defmt_mock.rs");
 
 #[cfg(test)]
 mod tests {
-    use embassy_sync::channel::Channel;
     use reqwless::client::HttpClient;
+    use rp2350_sensor_hub::config::sampling::DeviceConfig;
+    use rp2350_sensor_hub::device::DeviceId;
     use rp2350_sensor_hub::network::api;
     use rp2350_sensor_hub::network::error::SendMeasurementError;
-    use rp2350_sensor_hub::{Measurement, TempHumidityChannel};
+    use rp2350_sensor_hub::network::link::LinkDiagnostics;
+    use rp2350_sensor_hub::Measurement;
     use rstest::rstest;
-    use static_cell::StaticCell;
     use std_embedded_nal_async::Stack;
-    use wiremock::matchers::{basic_auth, body_json, header, method, path};
+    use wiremock::matchers::{
+        basic_auth, body_json, body_string, header, method, path, query_param,
+        query_param_is_missing,
+    };
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
-    static TEMP_HUMIDITY_CHANNEL: StaticCell<TempHumidityChannel> = StaticCell::new();
-
     const REST_USER: &str = env!("REST_USER");
     const REST_USER_PASSWORD: &str = env!("REST_USER_PASSWORD");
     const MEASUREMENTS_ENDPOINT: &str = env!("MEASUREMENTS_ENDPOINT");
 
-    async fn mock_measurements(mock_server: &MockServer, measurement: &Measurement) {
+    fn measurement(sensor: u8, temperature: f32) -> Measurement {
+        Measurement {
+            sensor,
+            temperature: Some(temperature),
+            ..Default::default()
+        }
+    }
+
+    async fn mock_batch(mock_server: &MockServer, measurements: &[Measurement]) {
         Mock::given(method("POST"))
             .and(basic_auth(REST_USER, REST_USER_PASSWORD))
             .and(header("Content-Type", "application/json"))
-            .and(path(MEASUREMENTS_ENDPOINT))
-            .and(body_json(measurement))
-            .respond_with(ResponseTemplate::new(201))
+            .and(path(format!("{}/batch", MEASUREMENTS_ENDPOINT)))
+            .and(body_json(measurements))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
             .mount(&mock_server)
             .await;
     }
@@ -36,22 +47,151 @@ mod tests {
     async fn network() -> Result<(), SendMeasurementError> {
         let mock_server = MockServer::start().await;
 
-        let measurement = Measurement {
-            temperature: 25.0,
-            humidity: 45.0,
+        let measurements = [Measurement {
+            sensor: 0,
+            temperature: Some(25.0),
+            probe: None,
+            humidity: Some(45.0),
+            pressure: Some(1013.2),
+            co2_ppm: Some(650),
+            pm1_0: None,
+            pm2_5: None,
+            pm10: None,
+            rejected: Some(2),
+            calibration: Some(1),
+            timestamp: Some(1_760_000_000_000),
+            device: Some(DeviceId::new(0xE661_4C31_1B2F_6A2D)),
+        }];
+        mock_batch(&mock_server, &measurements).await;
+
+        let host = mock_server.uri();
+
+        let stack = Stack::default();
+        let mut client = HttpClient::new(&stack, &stack);
+        let status_code = api::send_measurements(&mut client, &host, &measurements).await?;
+
+        mock_server.verify().await;
+        assert_eq!(status_code.0, 200);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn device_config() -> Result<(), SendMeasurementError> {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/config"))
+            .and(query_param("device", "0123456789abcdef"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("{\"sampling_interval_secs\":30}"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let host = mock_server.uri();
+
+        let stack = Stack::default();
+        let mut client = HttpClient::new(&stack, &stack);
+        let config = api::get_device_config(
+            &mut client,
+            &host,
+            Some(DeviceId::new(0x0123_4567_89ab_cdef)),
+        )
+        .await?;
+
+        assert_eq!(
+            config,
+            DeviceConfig {
+                sampling_interval_secs: Some(30),
+                ..Default::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn device_config_without_device() -> Result<(), SendMeasurementError> {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/config"))
+            .and(query_param_is_missing("device"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let host = mock_server.uri();
+
+        let stack = Stack::default();
+        let mut client = HttpClient::new(&stack, &stack);
+        let config = api::get_device_config(&mut client, &host, None).await?;
+
+        assert_eq!(config, DeviceConfig::default());
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn batch() -> Result<(), SendMeasurementError> {
+        let mock_server = MockServer::start().await;
+        let measurements = [measurement(0, 21.5), measurement(4, 22.0)];
+        mock_batch(&mock_server, &measurements).await;
+
+        let host = mock_server.uri();
+
+        let stack = Stack::default();
+        let mut client = HttpClient::new(&stack, &stack);
+        let status_code = api::send_measurements(&mut client, &host, &measurements).await?;
+
+        mock_server.verify().await;
+        assert_eq!(status_code.0, 200);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn diagnostics() -> Result<(), SendMeasurementError> {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(basic_auth(REST_USER, REST_USER_PASSWORD))
+            .and(path("/api/diagnostics"))
+            .and(body_string(
+                r#"{"device":"0123456789abcdef","link":{"reconnects":2,"link_losses":1,"lease_losses":1,"failed_joins":3}}"#,
+            ))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let diagnostics = LinkDiagnostics {
+            reconnects: 2,
+            link_losses: 1,
+            lease_losses: 1,
+            failed_joins: 3,
         };
-        mock_measurements(&mock_server, &measurement).await;
 
         let host = mock_server.uri();
 
         let stack = Stack::default();
         let mut client = HttpClient::new(&stack, &stack);
-        let channel = TEMP_HUMIDITY_CHANNEL.init(Channel::new());
-        channel.send(measurement).await;
-        let status_code = api::post_measurement(&mut client, &host, channel).await?;
+        let status_code = api::send_diagnostics(
+            &mut client,
+            &host,
+            Some(DeviceId::new(0x0123_4567_89AB_CDEF)),
+            &diagnostics,
+        )
+        .await?;
 
         mock_server.verify().await;
-        assert_eq!(status_code.0, 201);
+        assert_eq!(status_code.0, 204);
 
         Ok(())
     }
@@ -0,0 +1,252 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::pms5003::{
+        self, Command, FrameParser, Mode, ParticulateMatter, Pms5003, FRAME_LEN,
+    };
+    use embassy_dht_rp2350_sensor::{DHTSensorError, SensorValues};
+    use embedded_io_async::{ErrorKind, ErrorType, Read, ReadReady, Write};
+    use rstest::rstest;
+    use std::collections::VecDeque;
+
+    // Recorded from a sensor in active mode.
+    const FRAME: [u8; FRAME_LEN] = [
+        0x42, 0x4D, 0x00, 0x1C, 0x00, 0x05, 0x00, 0x09, 0x00, 0x0B, 0x00, 0x05, 0x00, 0x09, 0x00,
+        0x0B, 0x03, 0x7B, 0x01, 0x08, 0x00, 0x2D, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x97, 0x00,
+        0x02, 0x2D,
+    ];
+    const NEXT_FRAME: [u8; FRAME_LEN] = [
+        0x42, 0x4D, 0x00, 0x1C, 0x00, 0x0C, 0x00, 0x14, 0x00, 0x19, 0x00, 0x0B, 0x00, 0x12, 0x00,
+        0x17, 0x08, 0x64, 0x02, 0x80, 0x00, 0x70, 0x00, 0x0E, 0x00, 0x03, 0x00, 0x01, 0x97, 0x00,
+        0x03, 0x1F,
+    ];
+    // Answer to the command switching to passive mode.
+    const COMMAND_ANSWER: [u8; 8] = [0x42, 0x4D, 0x00, 0x04, 0xE1, 0x00, 0x01, 0x74];
+
+    const PM: ParticulateMatter = ParticulateMatter {
+        pm1_0: 5,
+        pm2_5: 9,
+        pm10: 11,
+    };
+    const NEXT_PM: ParticulateMatter = ParticulateMatter {
+        pm1_0: 11,
+        pm2_5: 18,
+        pm10: 23,
+    };
+
+    // Bytes already received are available at once, the sensor sends the later ones while the
+    // driver waits. Afterwards the sensor stays silent.
+    struct Uart {
+        received: VecDeque<u8>,
+        later: VecDeque<u8>,
+        written: Vec<u8>,
+    }
+
+    impl Uart {
+        fn new(received: &[u8], later: &[u8]) -> Self {
+            Uart {
+                received: received.iter().copied().collect(),
+                later: later.iter().copied().collect(),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl ErrorType for Uart {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Uart {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            if self.received.is_empty() {
+                if self.later.is_empty() {
+                    return std::future::pending().await;
+                }
+                self.received.append(&mut self.later);
+            }
+            let count = buf.len().min(self.received.len());
+            for (byte, received) in buf.iter_mut().zip(self.received.drain(..count)) {
+                *byte = received;
+            }
+            Ok(count)
+        }
+    }
+
+    impl ReadReady for Uart {
+        fn read_ready(&mut self) -> Result<bool, ErrorKind> {
+            Ok(!self.received.is_empty())
+        }
+    }
+
+    impl Write for Uart {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+    }
+
+    fn parse(stream: &[u8]) -> Vec<Result<ParticulateMatter, DHTSensorError>> {
+        let mut parser = FrameParser::new();
+        stream
+            .iter()
+            .filter_map(|byte| parser.push(*byte))
+            .collect()
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn decode() {
+        assert_eq!(pms5003::decode(&FRAME), Ok(PM));
+    }
+
+    #[rstest]
+    #[case::wrong_check_code(31, DHTSensorError::ChecksumError)]
+    #[case::wrong_data(5, DHTSensorError::ChecksumError)]
+    #[case::wrong_start_character(1, DHTSensorError::InvalidData)]
+    #[case::wrong_frame_length(3, DHTSensorError::InvalidData)]
+    #[test_log::test]
+    fn decode_invalid_frame(#[case] index: usize, #[case] error: DHTSensorError) {
+        let mut frame = FRAME;
+        frame[index] ^= 0x01;
+
+        assert_eq!(pms5003::decode(&frame), Err(error));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn parse_consecutive_frames() {
+        let stream = [FRAME, NEXT_FRAME].concat();
+
+        assert_eq!(parse(&stream), vec![Ok(PM), Ok(NEXT_PM)]);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn parse_from_the_middle_of_a_frame() {
+        let stream = [&NEXT_FRAME[13..], &FRAME[..]].concat();
+
+        assert_eq!(parse(&stream), vec![Ok(PM)]);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn parse_after_lost_bytes() {
+        // The first frame lost a byte, the second frame starts where its check code should be.
+        let stream = [&FRAME[..20], &FRAME[21..], &NEXT_FRAME[..]].concat();
+
+        assert_eq!(
+            parse(&stream),
+            vec![Err(DHTSensorError::ChecksumError), Ok(NEXT_PM)]
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn parse_skips_command_answers() {
+        let stream = [&COMMAND_ANSWER[..], &FRAME[..]].concat();
+
+        assert_eq!(parse(&stream), vec![Ok(PM)]);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn parse_frame_with_wrong_check_code() {
+        let mut frame = FRAME;
+        frame[10] ^= 0x01;
+        let stream = [frame, NEXT_FRAME].concat();
+
+        assert_eq!(
+            parse(&stream),
+            vec![Err(DHTSensorError::ChecksumError), Ok(NEXT_PM)]
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_drops_received_frames() {
+        let received = [&FRAME[..], &FRAME[..13]].concat();
+        let mut sensor = Pms5003::new(Uart::new(&received, &NEXT_FRAME));
+
+        assert_eq!(sensor.read().await, Ok(NEXT_PM));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_resyncs_after_a_corrupted_frame() {
+        let mut frame = FRAME;
+        frame[10] ^= 0x01;
+        let mut sensor = Pms5003::new(Uart::new(&[], &[frame, NEXT_FRAME].concat()));
+
+        assert_eq!(sensor.read().await, Ok(NEXT_PM));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_reports_a_corrupted_frame_at_the_timeout() {
+        let mut frame = FRAME;
+        frame[10] ^= 0x01;
+        let mut sensor = Pms5003::new(Uart::new(&[], &frame));
+
+        assert_eq!(sensor.read().await, Err(DHTSensorError::ChecksumError));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_requests_frame_in_passive_mode() {
+        let mut sensor = Pms5003::new(Uart::new(
+            &FRAME,
+            &[&COMMAND_ANSWER[..], &NEXT_FRAME].concat(),
+        ));
+
+        sensor.set_mode(Mode::Passive).await.unwrap();
+        assert_eq!(sensor.read().await, Ok(NEXT_PM));
+
+        let uart = sensor.release();
+        assert_eq!(
+            uart.written,
+            [
+                Command::SetMode(Mode::Passive).frame(),
+                Command::Read.frame()
+            ]
+            .concat()
+        );
+    }
+
+    #[rstest]
+    #[case::passive(Command::SetMode(Mode::Passive), [0x42, 0x4D, 0xE1, 0x00, 0x00, 0x01, 0x70])]
+    #[case::active(Command::SetMode(Mode::Active), [0x42, 0x4D, 0xE1, 0x00, 0x01, 0x01, 0x71])]
+    #[case::read(Command::Read, [0x42, 0x4D, 0xE2, 0x00, 0x00, 0x01, 0x71])]
+    #[case::sleep(Command::Sleep, [0x42, 0x4D, 0xE4, 0x00, 0x00, 0x01, 0x73])]
+    #[case::wake_up(Command::WakeUp, [0x42, 0x4D, 0xE4, 0x00, 0x01, 0x01, 0x74])]
+    #[test_log::test]
+    fn command_frame(#[case] command: Command, #[case] expected: [u8; 7]) {
+        assert_eq!(command.frame(), expected);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn particulate_matter_as_sensor_values() {
+        let values = SensorValues::from(ParticulateMatter {
+            pm1_0: 5,
+            pm2_5: 9,
+            pm10: 11,
+        });
+
+        assert_eq!(
+            values,
+            SensorValues {
+                pm1_0: Some(5),
+                pm2_5: Some(9),
+                pm10: Some(11),
+                ..Default::default()
+            }
+        );
+    }
+}
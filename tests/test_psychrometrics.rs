@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use psychrometrics::{self, Comfort, Psychrometrics};
+    use rstest::rstest;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::room(20.0, 50.0, 9.3)]
+    #[case::saturated(25.0, 100.0, 25.0)]
+    #[case::below_freezing(-10.0, 80.0, -12.8)]
+    #[case::tropical(30.0, 80.0, 26.2)]
+    #[test_log::test]
+    fn dew_point(#[case] temperature: f32, #[case] humidity: f32, #[case] expected: f32) {
+        assert_close(
+            psychrometrics::dew_point(temperature, humidity),
+            expected,
+            0.1,
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn dew_point_of_dry_air_is_finite() {
+        assert!(psychrometrics::dew_point(20.0, 0.0).is_finite());
+    }
+
+    // Reference values from the NOAA heat index chart, converted from °F.
+    #[rstest]
+    #[case::mild(20.0, 50.0, 19.4)]
+    #[case::hot(32.2, 70.0, 40.6)]
+    #[case::very_hot(37.8, 50.0, 47.8)]
+    #[test_log::test]
+    fn heat_index(#[case] temperature: f32, #[case] humidity: f32, #[case] expected: f32) {
+        assert_close(
+            psychrometrics::heat_index(temperature, humidity),
+            expected,
+            0.6,
+        );
+    }
+
+    #[rstest]
+    #[case::room(20.0, 50.0, 8.6)]
+    #[case::saturated(30.0, 100.0, 30.3)]
+    #[case::dry(20.0, 0.0, 0.0)]
+    #[test_log::test]
+    fn absolute_humidity(#[case] temperature: f32, #[case] humidity: f32, #[case] expected: f32) {
+        assert_close(
+            psychrometrics::absolute_humidity(temperature, humidity),
+            expected,
+            0.1,
+        );
+    }
+
+    #[rstest]
+    #[case::dry(22.0, 20.0, Comfort::Dry)]
+    #[case::comfortable(22.0, 45.0, Comfort::Comfortable)]
+    #[case::humid(25.0, 65.0, Comfort::Humid)]
+    #[case::oppressive(30.0, 75.0, Comfort::Oppressive)]
+    #[test_log::test]
+    fn comfort(#[case] temperature: f32, #[case] humidity: f32, #[case] expected: Comfort) {
+        assert_eq!(psychrometrics::comfort(temperature, humidity), expected);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn psychrometrics() {
+        let psychrometrics = Psychrometrics::new(20.0, 50.0);
+
+        assert_eq!(psychrometrics.comfort, Comfort::Comfortable);
+        assert_close(psychrometrics.dew_point, 9.3, 0.1);
+        assert_close(psychrometrics.heat_index, 19.4, 0.6);
+        assert_close(psychrometrics.absolute_humidity, 8.6, 0.1);
+    }
+}
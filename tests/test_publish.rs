@@ -0,0 +1,150 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::{Duration, Instant};
+    use rp2350_sensor_hub::publish::{PublishConfig, PublishPolicy};
+    use rp2350_sensor_hub::Measurement;
+    use rstest::{fixture, rstest};
+
+    const DHT_SENSOR: u8 = 0;
+    const PROBE_SENSOR: u8 = 6;
+
+    #[fixture]
+    fn policy() -> PublishPolicy {
+        PublishPolicy::new(PublishConfig {
+            temperature_deadband: 0.2,
+            humidity_deadband: 1.0,
+            pressure_deadband: 0.5,
+            co2_deadband: 20,
+            heartbeat: Duration::from_secs(600),
+        })
+    }
+
+    fn measurement(sensor: u8, temperature: f32, humidity: f32) -> Measurement {
+        Measurement {
+            sensor,
+            temperature: Some(temperature),
+            humidity: Some(humidity),
+            ..Default::default()
+        }
+    }
+
+    fn probe(probe: u64, temperature: f32) -> Measurement {
+        Measurement {
+            sensor: PROBE_SENSOR,
+            probe: Some(probe),
+            temperature: Some(temperature),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn first_measurement_is_published(mut policy: PublishPolicy) {
+        assert!(policy.should_publish(&measurement(DHT_SENSOR, 21.0, 40.0), Instant::from_secs(0)));
+    }
+
+    #[rstest]
+    #[case::unchanged(21.0, 40.0, false)]
+    #[case::temperature_within_deadband(21.1, 40.5, false)]
+    #[case::temperature_changed(21.2, 40.0, true)]
+    #[case::temperature_dropped(20.7, 40.0, true)]
+    #[case::humidity_changed(21.0, 41.5, true)]
+    #[test_log::test]
+    fn deadband(
+        mut policy: PublishPolicy,
+        #[case] temperature: f32,
+        #[case] humidity: f32,
+        #[case] expected: bool,
+    ) {
+        policy.should_publish(&measurement(DHT_SENSOR, 21.0, 40.0), Instant::from_secs(0));
+
+        assert_eq!(
+            policy.should_publish(
+                &measurement(DHT_SENSOR, temperature, humidity),
+                Instant::from_secs(10)
+            ),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn slow_drift_is_compared_to_last_published(mut policy: PublishPolicy) {
+        let published: Vec<bool> = [21.0, 21.1, 21.15, 21.25, 21.3]
+            .iter()
+            .enumerate()
+            .map(|(i, temperature)| {
+                policy.should_publish(
+                    &measurement(DHT_SENSOR, *temperature, 40.0),
+                    Instant::from_secs(i as u64 * 10),
+                )
+            })
+            .collect();
+
+        assert_eq!(published, [true, false, false, true, false]);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn heartbeat(mut policy: PublishPolicy) {
+        let unchanged = measurement(DHT_SENSOR, 21.0, 40.0);
+        policy.should_publish(&unchanged, Instant::from_secs(0));
+
+        assert!(!policy.should_publish(&unchanged, Instant::from_secs(590)));
+        assert!(policy.should_publish(&unchanged, Instant::from_secs(600)));
+        assert!(!policy.should_publish(&unchanged, Instant::from_secs(610)));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn sensors_and_probes_are_tracked_separately(mut policy: PublishPolicy) {
+        policy.should_publish(&measurement(DHT_SENSOR, 21.0, 40.0), Instant::from_secs(0));
+        policy.should_publish(&probe(0x28AA, 21.0), Instant::from_secs(0));
+
+        assert!(policy.should_publish(&measurement(1, 21.0, 40.0), Instant::from_secs(10)));
+        assert!(policy.should_publish(&probe(0x28BB, 21.0), Instant::from_secs(10)));
+        assert!(!policy.should_publish(&probe(0x28AA, 21.0), Instant::from_secs(10)));
+    }
+
+    #[rstest]
+    #[case::unchanged(1013.0, 600, false)]
+    #[case::within_deadbands(1013.4, 619, false)]
+    #[case::pressure_changed(1012.5, 600, true)]
+    #[case::co2_changed(1013.0, 620, true)]
+    #[case::co2_dropped(1013.0, 580, true)]
+    #[test_log::test]
+    fn pressure_and_co2_deadbands(
+        mut policy: PublishPolicy,
+        #[case] pressure: f32,
+        #[case] co2_ppm: u16,
+        #[case] expected: bool,
+    ) {
+        let measurement = |pressure, co2_ppm| Measurement {
+            sensor: 7,
+            pressure: Some(pressure),
+            co2_ppm: Some(co2_ppm),
+            ..Default::default()
+        };
+        assert!(policy.should_publish(&measurement(1013.0, 600), Instant::from_secs(0)));
+
+        assert_eq!(
+            policy.should_publish(&measurement(pressure, co2_ppm), Instant::from_secs(10)),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn particulate_matter_is_always_published(mut policy: PublishPolicy) {
+        let particulate_matter = Measurement {
+            sensor: 8,
+            pm2_5: Some(12),
+            ..Default::default()
+        };
+
+        assert!(policy.should_publish(&particulate_matter, Instant::from_secs(0)));
+        assert!(policy.should_publish(&particulate_matter, Instant::from_secs(10)));
+    }
+}
@@ -0,0 +1,314 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::Instant;
+    use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+    use rp2350_sensor_hub::clock::WallClock;
+    use rp2350_sensor_hub::device::DeviceId;
+    use rp2350_sensor_hub::queue::{MeasurementQueue, QueueError, RECORD_LEN};
+    use rp2350_sensor_hub::Measurement;
+    use rstest::rstest;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const SECTOR: usize = 4096;
+    const RECORDS_PER_SECTOR: u32 = (SECTOR / RECORD_LEN) as u32;
+    const OFFSET: u32 = 2 * SECTOR as u32;
+    const SIZE: u32 = 2 * SECTOR as u32;
+
+    // NOR flash in RAM, shared so that a queue can be "rebooted" on the same contents.
+    #[derive(Clone)]
+    struct RamFlash(Rc<RefCell<Vec<u8>>>);
+
+    impl RamFlash {
+        fn new() -> Self {
+            RamFlash(Rc::new(RefCell::new(vec![0xFF; (OFFSET + SIZE) as usize])))
+        }
+    }
+
+    impl ErrorType for RamFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.0.borrow()[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.borrow().len()
+        }
+    }
+
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 1;
+        const ERASE_SIZE: usize = SECTOR;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            if !(from as usize).is_multiple_of(SECTOR) || !(to as usize).is_multiple_of(SECTOR) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+            self.0.borrow_mut()[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        // Programming can only clear bits.
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            for (cell, byte) in self.0.borrow_mut()[offset..offset + bytes.len()]
+                .iter_mut()
+                .zip(bytes)
+            {
+                *cell &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    fn measurement(i: u32) -> Measurement {
+        Measurement {
+            sensor: (i % 4) as u8,
+            temperature: Some(20.0 + i as f32 / 10.0),
+            humidity: Some(40.0),
+            ..Default::default()
+        }
+    }
+
+    fn queue(flash: &RamFlash) -> MeasurementQueue<RamFlash> {
+        MeasurementQueue::new(flash.clone(), OFFSET, SIZE).unwrap()
+    }
+
+    fn drain(queue: &mut MeasurementQueue<RamFlash>) -> Vec<(Option<f32>, u64)> {
+        let mut drained = Vec::new();
+        while let Some(queued) = queue.peek() {
+            let queued = queued.unwrap();
+            drained.push((queued.measurement.temperature, queued.timestamp));
+            queue.pop().unwrap();
+        }
+        drained
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn empty_flash() {
+        let mut queue = queue(&RamFlash::new());
+
+        assert!(queue.is_empty());
+        assert!(queue.peek().is_none());
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn fifo() {
+        let mut queue = queue(&RamFlash::new());
+        for i in 0..3 {
+            queue.push(&measurement(i), 1000 * i as u64).unwrap();
+        }
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(
+            drain(&mut queue),
+            [(Some(20.0), 0), (Some(20.1), 1000), (Some(20.2), 2000)]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn round_trip_of_all_fields() {
+        let mut queue = queue(&RamFlash::new());
+        let measurement = Measurement {
+            sensor: 6,
+            probe: Some(0x2800_0000_DEAD_BEEF),
+            humidity: None,
+            temperature: Some(-3.5),
+            pressure: Some(1013.25),
+            co2_ppm: Some(650),
+            pm1_0: Some(1),
+            pm2_5: Some(2),
+            pm10: Some(3),
+            rejected: Some(5),
+            calibration: Some(7),
+            timestamp: Some(1_760_000_000_123),
+            device: Some(DeviceId::new(0xE661_4C31_1B2F_6A2D)),
+        };
+        queue.push(&measurement, 42).unwrap();
+
+        let queued = queue.peek().unwrap().unwrap();
+
+        assert_eq!(queued.timestamp, 42);
+        assert_eq!(queued.measurement.sensor, 6);
+        assert_eq!(queued.measurement.probe, Some(0x2800_0000_DEAD_BEEF));
+        assert_eq!(queued.measurement.humidity, None);
+        assert_eq!(queued.measurement.temperature, Some(-3.5));
+        assert_eq!(queued.measurement.pressure, Some(1013.25));
+        assert_eq!(queued.measurement.co2_ppm, Some(650));
+        assert_eq!(queued.measurement.pm1_0, Some(1));
+        assert_eq!(queued.measurement.pm2_5, Some(2));
+        assert_eq!(queued.measurement.pm10, Some(3));
+        assert_eq!(queued.measurement.rejected, None);
+        assert_eq!(queued.measurement.calibration, Some(7));
+        assert_eq!(queued.measurement.timestamp, Some(1_760_000_000_123));
+        assert_eq!(
+            queued.measurement.device,
+            Some(DeviceId::new(0xE661_4C31_1B2F_6A2D))
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn survives_reboot() {
+        let flash = RamFlash::new();
+        let mut before_reboot = queue(&flash);
+        for i in 0..4 {
+            before_reboot.push(&measurement(i), i as u64).unwrap();
+        }
+        before_reboot.pop().unwrap();
+
+        let mut after_reboot = queue(&flash);
+
+        assert_eq!(after_reboot.len(), 3);
+        after_reboot.push(&measurement(4), 4).unwrap();
+        assert_eq!(
+            drain(&mut after_reboot),
+            [
+                (Some(20.1), 1),
+                (Some(20.2), 2),
+                (Some(20.3), 3),
+                (Some(20.4), 4)
+            ]
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn backfill_only_measurements_of_the_current_boot() {
+        let flash = RamFlash::new();
+        queue(&flash).push(&measurement(0), 5_000).unwrap();
+        let mut after_reboot = queue(&flash);
+        after_reboot.push(&measurement(1), 5_000).unwrap();
+        let wall_clock = WallClock::new();
+        wall_clock.set(1_760_000_000_000, Instant::from_secs(10));
+
+        let backfilled: Vec<Option<u64>> = (0..2)
+            .map(|index| {
+                let mut queued = after_reboot.get(index).unwrap().unwrap();
+                queued.backfill(&wall_clock);
+                queued.measurement.timestamp
+            })
+            .collect();
+
+        assert_eq!(backfilled, [None, Some(1_759_999_995_000)]);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn get_behind_the_oldest() {
+        let mut queue = queue(&RamFlash::new());
+        for i in 0..3 {
+            queue.push(&measurement(i), i as u64).unwrap();
+        }
+        queue.pop().unwrap();
+
+        assert_eq!(queue.get(0).unwrap().unwrap().timestamp, 1);
+        assert_eq!(queue.get(1).unwrap().unwrap().timestamp, 2);
+        assert!(queue.get(2).is_none());
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn wraps_around() {
+        let flash = RamFlash::new();
+        let mut queue = queue(&flash);
+        // fill the first sector and send everything
+        for i in 0..RECORDS_PER_SECTOR {
+            queue.push(&measurement(i), i as u64).unwrap();
+        }
+        drain(&mut queue);
+        for i in 0..RECORDS_PER_SECTOR + 2 {
+            queue.push(&measurement(i), i as u64).unwrap();
+        }
+
+        assert_eq!(queue.dropped(), 0);
+        assert_eq!(queue.len(), RECORDS_PER_SECTOR + 2);
+        let drained = drain(&mut queue);
+        let timestamps: Vec<u64> = drained.iter().map(|(_, timestamp)| *timestamp).collect();
+        assert_eq!(
+            timestamps,
+            (0..RECORDS_PER_SECTOR as u64 + 2).collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn drops_oldest_sector_when_full() {
+        let flash = RamFlash::new();
+        let mut queue = queue(&flash);
+        let capacity = 2 * RECORDS_PER_SECTOR;
+        for i in 0..capacity + 1 {
+            queue.push(&measurement(i), i as u64).unwrap();
+        }
+
+        assert_eq!(queue.dropped(), RECORDS_PER_SECTOR);
+        assert_eq!(queue.len(), RECORDS_PER_SECTOR + 1);
+        assert_eq!(
+            queue.peek().unwrap().unwrap().timestamp,
+            RECORDS_PER_SECTOR as u64
+        );
+
+        let mut after_reboot = self::queue(&flash);
+        assert_eq!(after_reboot.len(), RECORDS_PER_SECTOR + 1);
+        let drained = drain(&mut after_reboot);
+        assert_eq!(
+            drained.last(),
+            Some(&(Some(20.0 + capacity as f32 / 10.0), capacity as u64))
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn skips_a_torn_slot_after_reboot() {
+        let flash = RamFlash::new();
+        let mut before_reboot = queue(&flash);
+        for i in 0..2 {
+            before_reboot.push(&measurement(i), i as u64).unwrap();
+        }
+        // power lost while the third record was half written
+        let torn = OFFSET as usize + 2 * RECORD_LEN;
+        flash.0.borrow_mut()[torn + 1..torn + RECORD_LEN / 2].fill(0x00);
+
+        let mut after_reboot = queue(&flash);
+        after_reboot.push(&measurement(2), 2).unwrap();
+
+        let mut after_second_reboot = queue(&flash);
+        assert_eq!(after_second_reboot.len(), 4);
+        assert_eq!(
+            after_second_reboot.get(2).map(|queued| queued.err()),
+            Some(Some(QueueError::ChecksumError))
+        );
+        assert_eq!(after_second_reboot.get(3).unwrap().unwrap().timestamp, 2);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn corrupted_record() {
+        let flash = RamFlash::new();
+        let mut queue = queue(&flash);
+        queue.push(&measurement(0), 0).unwrap();
+        queue.push(&measurement(1), 1).unwrap();
+        // flip a bit in the temperature of the first record
+        flash.0.borrow_mut()[OFFSET as usize + 30] ^= 0x01;
+
+        assert_eq!(
+            queue.peek().map(|queued| queued.err()),
+            Some(Some(QueueError::ChecksumError))
+        );
+        queue.pop().unwrap();
+        assert_eq!(queue.peek().unwrap().unwrap().timestamp, 1);
+    }
+}
@@ -0,0 +1,113 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::Duration;
+    use rp2350_sensor_hub::config::calibration::{Calibration, CalibrationEntry};
+    use rp2350_sensor_hub::config::error::ConfigError;
+    use rp2350_sensor_hub::config::sampling::{self, DeviceConfig};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::longer_than_minimum(60, 2, 60)]
+    #[case::equal_to_minimum(2, 2, 2)]
+    #[case::shorter_than_minimum(1, 2, 2)]
+    #[case::no_minimum(1, 0, 1)]
+    #[case::zero(0, 0, 1)]
+    #[case::longer_than_maximum(7200, 2, 3600)]
+    #[test_log::test]
+    fn clamp_interval(
+        #[case] interval_secs: u64,
+        #[case] min_request_interval_secs: u64,
+        #[case] expected_secs: u64,
+    ) {
+        assert_eq!(
+            sampling::clamp_interval(
+                Duration::from_secs(interval_secs),
+                Duration::from_secs(min_request_interval_secs)
+            ),
+            Duration::from_secs(expected_secs)
+        );
+    }
+
+    #[rstest]
+    #[case::interval(b"{\"sampling_interval_secs\":30}", Some(30))]
+    #[case::unset(b"{}", None)]
+    #[case::unknown_field(b"{\"sampling_interval_secs\":30,\"other\":true}", Some(30))]
+    #[test_log::test]
+    fn device_config_from_json(#[case] body: &[u8], #[case] expected: Option<u64>) {
+        assert_eq!(
+            DeviceConfig::from_json(body),
+            Ok(DeviceConfig {
+                sampling_interval_secs: expected,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::in_range(Some(30), Some(30))]
+    #[case::zero(Some(0), Some(1))]
+    #[case::longer_than_maximum(Some(u64::MAX), Some(3600))]
+    #[case::unset(None, None)]
+    #[test_log::test]
+    fn sampling_interval(#[case] secs: Option<u64>, #[case] expected_secs: Option<u64>) {
+        let config = DeviceConfig {
+            sampling_interval_secs: secs,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.sampling_interval(),
+            expected_secs.map(Duration::from_secs)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn device_config_with_calibration() {
+        let body = br#"{"calibration":{"version":4,"sensors":[
+            {"sensor":1,"calibration":{"temperature_offset":-0.5}},
+            {"sensor":6,"probe":"28000316a2791eff","calibration":{"temperature_gain":1.02}}
+        ]}}"#;
+
+        let calibration = DeviceConfig::from_json(body).unwrap().calibration.unwrap();
+
+        assert_eq!(calibration.version, 4);
+        assert_eq!(
+            calibration.sensors.as_slice(),
+            [
+                CalibrationEntry {
+                    sensor: 1,
+                    probe: None,
+                    calibration: Calibration {
+                        temperature_offset: -0.5,
+                        ..Default::default()
+                    },
+                },
+                CalibrationEntry {
+                    sensor: 6,
+                    probe: Some(0x2800_0316_A279_1EFF),
+                    calibration: Calibration {
+                        temperature_gain: 1.02,
+                        ..Default::default()
+                    },
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::negative(b"{\"sampling_interval_secs\":-1}")]
+    #[case::truncated(b"{\"sampling_interval_secs\":")]
+    #[case::probe_as_number(
+        b"{\"calibration\":{\"version\":1,\"sensors\":[{\"sensor\":6,\"probe\":42}]}}"
+    )]
+    #[case::short_probe(
+        b"{\"calibration\":{\"version\":1,\"sensors\":[{\"sensor\":6,\"probe\":\"2a\"}]}}"
+    )]
+    #[test_log::test]
+    fn invalid_device_config(#[case] body: &[u8]) {
+        assert_eq!(DeviceConfig::from_json(body), Err(ConfigError::InvalidData));
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::scd4x::{Scd4x, DEFAULT_ADDRESS};
+    use embassy_dht_rp2350_sensor::{DHTSensorError, EnvironmentalSensor, SensorError};
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use rstest::rstest;
+
+    // 500 ppm, 25.0 degrees and 50% humidity, each word followed by its CRC.
+    const MEASUREMENT: [u8; 9] = [0x01, 0xF4, 0x33, 0x66, 0x66, 0x93, 0x80, 0x00, 0xA2];
+
+    fn command(command: &[u8], response: &[u8]) -> Vec<Transaction> {
+        vec![
+            Transaction::write(DEFAULT_ADDRESS, command.to_vec()),
+            Transaction::read(DEFAULT_ADDRESS, response.to_vec()),
+        ]
+    }
+
+    fn start() -> Transaction {
+        Transaction::write(DEFAULT_ADDRESS, vec![0x21, 0xB1])
+    }
+
+    fn stop() -> Transaction {
+        Transaction::write(DEFAULT_ADDRESS, vec![0x3F, 0x86])
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read() {
+        let mut transactions = vec![start()];
+        transactions.extend(command(&[0xE4, 0xB8], &[0x80, 0x05, 0x57]));
+        transactions.extend(command(&[0xEC, 0x05], &MEASUREMENT));
+        // The periodic measurement is only started once.
+        transactions.extend(command(&[0xE4, 0xB8], &[0x80, 0x05, 0x57]));
+        transactions.extend(command(&[0xEC, 0x05], &MEASUREMENT));
+        let i2c = Mock::new(&transactions);
+        let mut sensor = Scd4x::start(i2c, NoopDelay::new()).await;
+
+        for _ in 0..2 {
+            let reading = EnvironmentalSensor::read(&mut sensor).await.unwrap();
+            assert!(!reading.is_cached);
+            assert_eq!(reading.values.co2_ppm, Some(500));
+            assert!((reading.values.temperature.unwrap() - 25.0).abs() < 0.01);
+            assert!((reading.values.humidity.unwrap() - 50.0).abs() < 0.01);
+        }
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_without_new_data() {
+        let mut transactions = vec![start()];
+        transactions.extend(command(&[0xE4, 0xB8], &[0x80, 0x00, 0xA2]));
+        let i2c = Mock::new(&transactions);
+        let mut sensor = Scd4x::start(i2c, NoopDelay::new()).await;
+
+        assert_eq!(
+            EnvironmentalSensor::read(&mut sensor).await,
+            Err(SensorError::NoData)
+        );
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_restarts_failed_start() {
+        let mut transactions = vec![start().with_error(ErrorKind::Other), start()];
+        transactions.extend(command(&[0xE4, 0xB8], &[0x80, 0x05, 0x57]));
+        transactions.extend(command(&[0xEC, 0x05], &MEASUREMENT));
+        let i2c = Mock::new(&transactions);
+        let mut sensor = Scd4x::start(i2c, NoopDelay::new()).await;
+
+        assert_eq!(
+            EnvironmentalSensor::read(&mut sensor).await,
+            Err(SensorError::NoData)
+        );
+        let reading = EnvironmentalSensor::read(&mut sensor).await.unwrap();
+        assert_eq!(reading.values.co2_ppm, Some(500));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read_measurement_with_invalid_crc() {
+        let mut measurement = MEASUREMENT;
+        measurement[5] ^= 0x01;
+        let i2c = Mock::new(&command(&[0xEC, 0x05], &measurement));
+        let mut sensor = Scd4x::new(i2c, NoopDelay::new());
+
+        assert_eq!(
+            sensor.read_measurement().await,
+            Err(DHTSensorError::ChecksumError)
+        );
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[case::applied([0x7F, 0xF0, 0xA1], Ok(-16))]
+    #[case::failed([0xFF, 0xFF, 0xAC], Err(DHTSensorError::InvalidData))]
+    #[tokio::test]
+    #[test_log::test]
+    async fn forced_recalibration(
+        #[case] response: [u8; 3],
+        #[case] expected: Result<i16, DHTSensorError>,
+    ) {
+        let mut transactions = vec![start(), stop()];
+        transactions.extend(command(&[0x36, 0x2F, 0x01, 0x90, 0x4C], &response));
+        transactions.push(start());
+        let i2c = Mock::new(&transactions);
+        let mut sensor = Scd4x::start(i2c, NoopDelay::new()).await;
+
+        assert_eq!(sensor.forced_recalibration(400).await, expected);
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn compensation() {
+        let i2c = Mock::new(&[
+            start(),
+            stop(),
+            Transaction::write(DEFAULT_ADDRESS, vec![0x24, 0x27, 0x03, 0xE8, 0xD4]),
+            start(),
+            Transaction::write(DEFAULT_ADDRESS, vec![0xE0, 0x00, 0x03, 0xF1, 0x1F]),
+        ]);
+        let mut sensor = Scd4x::start(i2c, NoopDelay::new()).await;
+
+        sensor.set_altitude(1000).await.unwrap();
+        sensor.set_ambient_pressure(1009.6).await.unwrap();
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn serial_number() {
+        let i2c = Mock::new(&command(
+            &[0x36, 0x82],
+            &[0x12, 0x34, 0x37, 0x56, 0x78, 0x7D, 0x9A, 0xBC, 0xE0],
+        ));
+        let mut sensor = Scd4x::new(i2c, NoopDelay::new());
+
+        assert_eq!(sensor.serial_number().await, Ok(0x1234_5678_9ABC));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use embassy_dht_rp2350_sensor::sht4x::{
+        self, HeaterDuration, HeaterPower, Precision, Sht4x, DEFAULT_ADDRESS,
+    };
+    use embassy_dht_rp2350_sensor::{DHTSensorError, DTHResponse, EnvironmentalSensor};
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use rstest::rstest;
+
+    // 25.0 degrees and 56.5% humidity, each word followed by its CRC.
+    const MEASUREMENT: [u8; 6] = [0x66, 0x66, 0x93, 0x80, 0x00, 0xA2];
+
+    fn command(command: u8, response: [u8; 6]) -> Vec<Transaction> {
+        vec![
+            Transaction::write(DEFAULT_ADDRESS, vec![command]),
+            Transaction::read(DEFAULT_ADDRESS, response.to_vec()),
+        ]
+    }
+
+    fn assert_response(response: DTHResponse, temperature: f32, humidity: f32) {
+        assert!((response.temperature - temperature).abs() < 0.01);
+        assert!((response.humidity - humidity).abs() < 0.01);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn crc8() {
+        // Example from the datasheet
+        assert_eq!(sht4x::crc8(&[0xBE, 0xEF]), 0x92);
+    }
+
+    #[rstest]
+    #[case::high(Precision::High, 0xFD)]
+    #[case::medium(Precision::Medium, 0xF6)]
+    #[case::low(Precision::Low, 0xE0)]
+    #[tokio::test]
+    #[test_log::test]
+    async fn measure(#[case] precision: Precision, #[case] expected_command: u8) {
+        let i2c = Mock::new(&command(expected_command, MEASUREMENT));
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+        sensor.set_precision(precision);
+
+        let response = sensor.measure().await.unwrap();
+        assert_response(response, 25.0, 56.5);
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[case::above_range([0x66, 0x66, 0x93, 0xFF, 0xFF, 0xAC], 100.0)]
+    #[case::below_range([0x66, 0x66, 0x93, 0x00, 0x00, 0x81], 0.0)]
+    #[tokio::test]
+    #[test_log::test]
+    async fn measure_clips_humidity(#[case] data: [u8; 6], #[case] expected_humidity: f32) {
+        let i2c = Mock::new(&command(0xFD, data));
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+
+        let response = sensor.measure().await.unwrap();
+        assert_response(response, 25.0, expected_humidity);
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[case::temperature([0x66, 0x66, 0x94, 0x80, 0x00, 0xA2])]
+    #[case::humidity([0x66, 0x66, 0x93, 0x80, 0x00, 0xA3])]
+    #[tokio::test]
+    #[test_log::test]
+    async fn measure_with_invalid_crc(#[case] data: [u8; 6]) {
+        let i2c = Mock::new(&command(0xFD, data));
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+
+        assert_eq!(sensor.measure().await, Err(DHTSensorError::ChecksumError));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn measure_without_acknowledge() {
+        let i2c = Mock::new(&[Transaction::write(DEFAULT_ADDRESS, vec![0xFD])
+            .with_error(embedded_hal::i2c::ErrorKind::Other)]);
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+
+        assert_eq!(sensor.measure().await, Err(DHTSensorError::BusError));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn read() {
+        let i2c = Mock::new(&command(0xFD, MEASUREMENT));
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+
+        let reading = EnvironmentalSensor::read(&mut sensor).await.unwrap();
+        assert!(!reading.is_cached);
+        assert!((reading.values.temperature.unwrap() - 25.0).abs() < 0.01);
+        assert!((reading.values.humidity.unwrap() - 56.5).abs() < 0.01);
+        assert_eq!(reading.values.pressure, None);
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[case::high_long(HeaterPower::High, HeaterDuration::Long, 0x39)]
+    #[case::medium_short(HeaterPower::Medium, HeaterDuration::Short, 0x24)]
+    #[case::low_short(HeaterPower::Low, HeaterDuration::Short, 0x15)]
+    #[tokio::test]
+    #[test_log::test]
+    async fn heat(
+        #[case] power: HeaterPower,
+        #[case] duration: HeaterDuration,
+        #[case] expected_command: u8,
+    ) {
+        let i2c = Mock::new(&command(expected_command, MEASUREMENT));
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+
+        let response = sensor.heat(power, duration).await.unwrap();
+        assert_response(response, 25.0, 56.5);
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn serial_number() {
+        let i2c = Mock::new(&command(0x89, [0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]));
+        let mut sensor = Sht4x::new(i2c, NoopDelay::new());
+
+        assert_eq!(sensor.serial_number().await, Ok(0x1234_5678));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    #[test_log::test]
+    async fn soft_reset() {
+        let i2c = Mock::new(&[Transaction::write(0x45, vec![0x94])]);
+        let mut sensor = Sht4x::new_with_address(i2c, NoopDelay::new(), 0x45);
+
+        assert_eq!(sensor.soft_reset().await, Ok(()));
+
+        let (mut i2c, _) = sensor.release();
+        i2c.done();
+    }
+}
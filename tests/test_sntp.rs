@@ -0,0 +1,120 @@
+include!("common/defmt_mock.rs");
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::Instant;
+    use rp2350_sensor_hub::clock::WallClock;
+    use rp2350_sensor_hub::network::error::SntpError;
+    use rp2350_sensor_hub::network::sntp::{self, PACKET_LEN};
+    use rstest::rstest;
+
+    // 2025-10-09T08:53:20Z
+    const NTP_SECONDS: u32 = 3_968_988_800;
+    const UNIX_MILLIS: u64 = 1_760_000_000_000;
+    const TRANSMIT: u64 = 0x0123_4567_89AB_CDEF;
+
+    fn response(first_byte: u8, stratum: u8, seconds: u32, fraction: u32) -> [u8; PACKET_LEN] {
+        let mut packet = [0; PACKET_LEN];
+        packet[0] = first_byte;
+        packet[1] = stratum;
+        packet[24..32].copy_from_slice(&TRANSMIT.to_be_bytes());
+        packet[40..44].copy_from_slice(&seconds.to_be_bytes());
+        packet[44..48].copy_from_slice(&fraction.to_be_bytes());
+        packet
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn request_is_a_version_4_client_packet() {
+        let request = sntp::request(TRANSMIT);
+
+        assert_eq!(request[0], 0x23);
+        assert!(request[1..40].iter().all(|&byte| byte == 0));
+        assert_eq!(request[40..], TRANSMIT.to_be_bytes());
+    }
+
+    #[rstest]
+    #[case::whole_second(0, UNIX_MILLIS)]
+    #[case::half_second(0x8000_0000, UNIX_MILLIS + 500)]
+    #[test_log::test]
+    fn parse_response(#[case] fraction: u32, #[case] expected: u64) {
+        let packet = response(0x24, 2, NTP_SECONDS, fraction);
+
+        assert_eq!(sntp::parse_response(&packet, TRANSMIT), Ok(expected));
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn parse_response_after_the_era_rollover() {
+        // 2036-02-07T06:28:17Z, one second into the next NTP era
+        let packet = response(0x24, 2, 1, 0);
+
+        assert_eq!(
+            sntp::parse_response(&packet, TRANSMIT),
+            Ok(2_085_978_497_000)
+        );
+    }
+
+    #[rstest]
+    #[case::client_mode(response(0x23, 2, NTP_SECONDS, 0))]
+    #[case::not_synchronised(response(0xE4, 2, NTP_SECONDS, 0))]
+    #[case::kiss_of_death(response(0x24, 0, NTP_SECONDS, 0))]
+    #[case::no_transmit_time(response(0x24, 2, 0, 0))]
+    #[case::before_the_unix_epoch(response(0x24, 2, 0x8000_0000, 0))]
+    #[test_log::test]
+    fn rejects_invalid_responses(#[case] packet: [u8; PACKET_LEN]) {
+        assert_eq!(
+            sntp::parse_response(&packet, TRANSMIT),
+            Err(SntpError::InvalidResponse)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn rejects_responses_to_an_other_request() {
+        let packet = response(0x24, 2, NTP_SECONDS, 0);
+
+        assert_eq!(
+            sntp::parse_response(&packet, TRANSMIT + 1),
+            Err(SntpError::InvalidResponse)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn rejects_short_responses() {
+        let packet = response(0x24, 2, NTP_SECONDS, 0);
+
+        assert_eq!(
+            sntp::parse_response(&packet[..PACKET_LEN - 1], TRANSMIT),
+            Err(SntpError::InvalidResponse)
+        );
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn wall_clock_is_unknown_until_set() {
+        let wall_clock = WallClock::new();
+
+        assert!(!wall_clock.is_set());
+        assert_eq!(wall_clock.at(Instant::from_secs(5)), None);
+    }
+
+    #[rstest]
+    #[test_log::test]
+    fn wall_clock_keeps_the_offset_over_the_uptime() {
+        let wall_clock = WallClock::new();
+        wall_clock.set(UNIX_MILLIS, Instant::from_secs(5));
+
+        assert!(wall_clock.is_set());
+        assert_eq!(wall_clock.at(Instant::from_secs(5)), Some(UNIX_MILLIS));
+        assert_eq!(
+            wall_clock.at(Instant::from_millis(65_250)),
+            Some(UNIX_MILLIS + 60_250)
+        );
+        assert_eq!(
+            wall_clock.at(Instant::from_secs(0)),
+            Some(UNIX_MILLIS - 5_000)
+        );
+    }
+}